@@ -25,8 +25,18 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             "VerifierInfo",
             "#[derive(serde::Serialize, serde::Deserialize)]",
         )
+        .type_attribute(
+            "PreKeyBundle",
+            "#[derive(serde::Serialize, serde::Deserialize)]",
+        )
         .file_descriptor_set_path(original_out_dir.join("descriptor.bin"))
-        .compile(&["proto/karma_coin/verifier.proto"], &["proto"])
+        .compile(
+            &[
+                "proto/karma_coin/verifier.proto",
+                "proto/karma_coin/signer.proto",
+            ],
+            &["proto"],
+        )
         .unwrap_or_else(|e| panic!("Failed to compile protos {:?}", e));
 
     let src = Path::new("src/karma_coin");
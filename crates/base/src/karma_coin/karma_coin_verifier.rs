@@ -3,6 +3,13 @@
 pub struct SendVerificationCodeRequest {
     #[prost(string, tag = "1")]
     pub mobile_number: ::prost::alloc::string::String,
+    /// Code delivery channel. The verifier's default channel is used when unspecified
+    #[prost(enumeration = "VerificationChannel", tag = "2")]
+    pub channel: i32,
+    /// protobuf serialized SendVerificationCodeRequest encrypted to the verifier's pre-key bundle.
+    /// The other fields must be unset when provided
+    #[prost(message, optional, tag = "3")]
+    pub encrypted_request: ::core::option::Option<EncryptedPayload>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -13,6 +20,9 @@ pub struct SendVerificationCodeResponse {
     pub session_id: ::prost::alloc::string::String,
     #[prost(string, tag = "3")]
     pub error_message: ::prost::alloc::string::String,
+    /// channel the code was sent with - may differ from the requested one when the verifier failed over
+    #[prost(enumeration = "VerificationChannel", tag = "4")]
+    pub channel: i32,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -24,36 +34,47 @@ pub struct VerifyNumberRequest {
     /// Public key is account_id in the data
     #[prost(bytes = "vec", tag = "2")]
     pub signature: ::prost::alloc::vec::Vec<u8>,
+    /// protobuf serialized VerifyNumberRequestData encrypted to the verifier's pre-key bundle.
+    /// data must be empty when provided and the signature is of the decrypted data
+    #[prost(message, optional, tag = "3")]
+    pub encrypted_data: ::core::option::Option<EncryptedPayload>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct VerifyNumberResponse {
-    /// serialized SIGNED UserVerificationData. This data should be scale and not protobuf encoded
+    /// verifier's signature of the scale encoded evidence. Same as user_verification_data.signature
     #[prost(bytes = "vec", tag = "1")]
     pub data: ::prost::alloc::vec::Vec<u8>,
     /// verification result for client feedback
     #[prost(enumeration = "VerificationResult", tag = "3")]
     pub result: i32,
+    /// time the signed evidence was issued at in milliseconds
+    #[prost(uint64, tag = "4")]
+    pub issued_at: u64,
+    /// time the signed evidence expires at in milliseconds
+    #[prost(uint64, tag = "5")]
+    pub expires_at: u64,
+    /// signed evidence. Set when the number is verified
+    #[prost(message, optional, tag = "6")]
+    pub user_verification_data: ::core::option::Option<UserVerificationData>,
 }
 /// Created and signed by a verifier to attest that an account owns a mobile number
-/// Includes mobile number hash instead of mobile number in response
-/// Signature is externally available
-/// todo: this should be scale encoded
+/// Includes mobile number hash instead of mobile number
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
 pub struct UserVerificationData {
-    #[prost(string, tag = "1")]
-    pub verifier_account_id: ::prost::alloc::string::String,
-    #[prost(uint64, tag = "2")]
-    pub timestamp: u64,
-    #[prost(string, tag = "3")]
-    pub account_id: ::prost::alloc::string::String,
-    #[prost(string, tag = "4")]
-    pub phone_number_hash: ::prost::alloc::string::String,
-    #[prost(string, tag = "5")]
-    pub user_name: ::prost::alloc::string::String,
-    #[prost(string, tag = "6")]
-    pub signature: ::prost::alloc::string::String,
+    /// scale encoded verification evidence - forwarded to the chain verbatim
+    /// layout: verifier_public_key (32 bytes) || account_id (32 bytes) ||
+    /// user_name (compact length prefixed utf8) || phone_number_hash_scheme (1 byte PhoneNumberHashScheme value) ||
+    /// phone_number_hash (64 bytes) || issued_at (u64 le) || expires_at (u64 le)
+    #[prost(bytes = "vec", tag = "1")]
+    pub evidence: ::prost::alloc::vec::Vec<u8>,
+    /// verifier's ed25519 public key
+    #[prost(bytes = "vec", tag = "2")]
+    pub verifier_public_key: ::prost::alloc::vec::Vec<u8>,
+    /// verifier's ed25519 signature of the evidence bytes
+    #[prost(bytes = "vec", tag = "3")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
 }
 #[allow(clippy::derive_partial_eq_without_eq)]
 #[derive(Clone, PartialEq, ::prost::Message)]
@@ -76,6 +97,140 @@ pub struct VerifyNumberRequestData {
     #[prost(string, tag = "7")]
     pub verification_sid: ::prost::alloc::string::String,
 }
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyEvidenceRequest {
+    /// scale encoded verification evidence as returned in UserVerificationData
+    #[prost(bytes = "vec", tag = "1")]
+    pub evidence: ::prost::alloc::vec::Vec<u8>,
+    /// verifier's ed25519 signature of the evidence bytes
+    #[prost(bytes = "vec", tag = "2")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifyEvidenceResponse {
+    #[prost(enumeration = "EvidenceStatus", tag = "1")]
+    pub status: i32,
+    /// true when the evidence was signed by the verifier's current key and false for a past key
+    #[prost(bool, tag = "2")]
+    pub signed_by_current_key: bool,
+    /// the following fields are set when the evidence is signed by this verifier
+    #[prost(bytes = "vec", tag = "3")]
+    pub verifier_public_key: ::prost::alloc::vec::Vec<u8>,
+    /// ss58 account id the evidence binds
+    #[prost(string, tag = "4")]
+    pub account_id: ::prost::alloc::string::String,
+    #[prost(string, tag = "5")]
+    pub user_name: ::prost::alloc::string::String,
+    #[prost(bytes = "vec", tag = "6")]
+    pub phone_number_hash: ::prost::alloc::vec::Vec<u8>,
+    /// time the evidence was issued at in milliseconds
+    #[prost(uint64, tag = "7")]
+    pub issued_at: u64,
+    /// time the evidence expires at in milliseconds
+    #[prost(uint64, tag = "8")]
+    pub expires_at: u64,
+    /// scheme the phone number hash was computed with
+    #[prost(enumeration = "PhoneNumberHashScheme", tag = "9")]
+    pub phone_number_hash_scheme: i32,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetVerifierInfoRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetVerifierInfoResponse {
+    #[prost(message, optional, tag = "1")]
+    pub verifier_info: ::core::option::Option<VerifierInfo>,
+}
+/// Verifier identity and capabilities - used by clients to discover and pin verifiers
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct VerifierInfo {
+    /// verifier's display name
+    #[prost(string, tag = "1")]
+    pub name: ::prost::alloc::string::String,
+    /// verifier's ed25519 public key evidence is signed with
+    #[prost(bytes = "vec", tag = "2")]
+    pub public_key: ::prost::alloc::vec::Vec<u8>,
+    /// ss58 encoding of the public key
+    #[prost(string, tag = "3")]
+    pub public_key_ss58: ::prost::alloc::string::String,
+    /// ed25519 public keys the verifier signed evidence with in the past
+    #[prost(bytes = "vec", repeated, tag = "4")]
+    pub past_public_keys: ::prost::alloc::vec::Vec<::prost::alloc::vec::Vec<u8>>,
+    /// code delivery channels users may request
+    #[prost(enumeration = "VerificationChannel", repeated, tag = "5")]
+    pub channels: ::prost::alloc::vec::Vec<i32>,
+    /// formats of the evidence the verifier issues
+    #[prost(enumeration = "EvidenceFormat", repeated, tag = "6")]
+    pub evidence_formats: ::prost::alloc::vec::Vec<i32>,
+    /// verifier api version
+    #[prost(string, tag = "7")]
+    pub api_version: ::prost::alloc::string::String,
+    /// scheme phone number hashes of issued evidence are computed with
+    #[prost(enumeration = "PhoneNumberHashScheme", tag = "8")]
+    pub phone_number_hash_scheme: i32,
+    /// pre-key bundle clients encrypt requests to
+    #[prost(message, optional, tag = "9")]
+    pub pre_key_bundle: ::core::option::Option<PreKeyBundle>,
+}
+/// X2DH pre-key bundle clients encrypt phone numbers to, so they are protected where TLS is terminated by a proxy.
+/// Signed by the verifier's signing key so clients can authenticate it with the verifier's pinned public key
+#[derive(serde::Serialize, serde::Deserialize)]
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct PreKeyBundle {
+    /// bundle id - provided in payloads encrypted to the bundle
+    #[prost(uint64, tag = "1")]
+    pub bundle_id: u64,
+    /// verifier's X2DH ed25519 identity key
+    #[prost(bytes = "vec", tag = "2")]
+    pub identity_key: ::prost::alloc::vec::Vec<u8>,
+    /// verifier's x25519 pre-key
+    #[prost(bytes = "vec", tag = "3")]
+    pub pre_key: ::prost::alloc::vec::Vec<u8>,
+    /// ed25519 public key of the verifier signing key which signed the bundle
+    #[prost(bytes = "vec", tag = "4")]
+    pub verifier_public_key: ::prost::alloc::vec::Vec<u8>,
+    /// signature of "karmacoin-pre-key-bundle" || bundle_id || identity_key || pre_key || expires_at
+    /// with bundle_id and expires_at encoded as 8 bytes little endian
+    #[prost(bytes = "vec", tag = "5")]
+    pub signature: ::prost::alloc::vec::Vec<u8>,
+    /// time the bundle was created at in milliseconds
+    #[prost(uint64, tag = "6")]
+    pub created_at: u64,
+    /// time after which the verifier no longer decrypts payloads encrypted to the bundle in milliseconds.
+    /// The verifier publishes a new bundle ahead of it
+    #[prost(uint64, tag = "7")]
+    pub expires_at: u64,
+}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetPreKeyBundleRequest {}
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct GetPreKeyBundleResponse {
+    #[prost(message, optional, tag = "1")]
+    pub pre_key_bundle: ::core::option::Option<PreKeyBundle>,
+}
+/// A payload encrypted to a verifier pre-key bundle. The sender executes X2DH with the bundle's keys and
+/// encrypts the payload with the crypto crate's AeadCipher keyed with the shared secret - XChaCha20-Poly1305
+/// with a random nonce, framed as version (1) || nonce (24 bytes) || ciphertext || tag
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct EncryptedPayload {
+    /// id of the pre-key bundle the payload is encrypted to
+    #[prost(uint64, tag = "1")]
+    pub bundle_id: u64,
+    /// sender's x25519 ephemeral public key
+    #[prost(bytes = "vec", tag = "2")]
+    pub ephemeral_key: ::prost::alloc::vec::Vec<u8>,
+    #[prost(bytes = "vec", tag = "3")]
+    pub ciphertext: ::prost::alloc::vec::Vec<u8>,
+}
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
 #[repr(i32)]
 pub enum SendVerificationCodeResult {
@@ -83,6 +238,12 @@ pub enum SendVerificationCodeResult {
     Sent = 1,
     Failed = 2,
     InvalidUserData = 3,
+    /// requested channel is not enabled by the verifier
+    ChannelNotSupported = 4,
+    /// verifier doesn't send codes to the number's country
+    RegionNotSupported = 5,
+    /// encrypted request can't be decrypted - the pre-key bundle should be fetched again
+    InvalidEncryption = 6,
 }
 impl SendVerificationCodeResult {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -99,6 +260,15 @@ impl SendVerificationCodeResult {
             SendVerificationCodeResult::InvalidUserData => {
                 "SEND_VERIFICATION_CODE_RESULT_INVALID_USER_DATA"
             }
+            SendVerificationCodeResult::ChannelNotSupported => {
+                "SEND_VERIFICATION_CODE_RESULT_CHANNEL_NOT_SUPPORTED"
+            }
+            SendVerificationCodeResult::RegionNotSupported => {
+                "SEND_VERIFICATION_CODE_RESULT_REGION_NOT_SUPPORTED"
+            }
+            SendVerificationCodeResult::InvalidEncryption => {
+                "SEND_VERIFICATION_CODE_RESULT_INVALID_ENCRYPTION"
+            }
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -110,6 +280,15 @@ impl SendVerificationCodeResult {
             "SEND_VERIFICATION_CODE_RESULT_INVALID_USER_DATA" => {
                 Some(Self::InvalidUserData)
             }
+            "SEND_VERIFICATION_CODE_RESULT_CHANNEL_NOT_SUPPORTED" => {
+                Some(Self::ChannelNotSupported)
+            }
+            "SEND_VERIFICATION_CODE_RESULT_REGION_NOT_SUPPORTED" => {
+                Some(Self::RegionNotSupported)
+            }
+            "SEND_VERIFICATION_CODE_RESULT_INVALID_ENCRYPTION" => {
+                Some(Self::InvalidEncryption)
+            }
             _ => None,
         }
     }
@@ -125,6 +304,20 @@ pub enum VerificationResult {
     Failed = 5,
     /// bad client signature
     InvalidSignature = 6,
+    /// verification session is unknown, expired or was issued to another number
+    InvalidSession = 7,
+    /// request timestamp is outside of the accepted time window
+    StaleRequest = 8,
+    /// signed request was already submitted
+    DuplicateRequest = 9,
+    /// verifier doesn't verify numbers of the number's country
+    RegionNotSupported = 10,
+    /// session is locked after too many wrong codes - a new code should be requested
+    TooManyAttempts = 11,
+    /// user name doesn't meet the verifier's user name policy
+    InvalidUserName = 12,
+    /// encrypted data can't be decrypted - the pre-key bundle should be fetched again
+    InvalidEncryption = 13,
 }
 impl VerificationResult {
     /// String value of the enum field names used in the ProtoBuf definition.
@@ -140,6 +333,19 @@ impl VerificationResult {
             VerificationResult::InvalidSignature => {
                 "VERIFICATION_RESULT_INVALID_SIGNATURE"
             }
+            VerificationResult::InvalidSession => "VERIFICATION_RESULT_INVALID_SESSION",
+            VerificationResult::StaleRequest => "VERIFICATION_RESULT_STALE_REQUEST",
+            VerificationResult::DuplicateRequest => {
+                "VERIFICATION_RESULT_DUPLICATE_REQUEST"
+            }
+            VerificationResult::RegionNotSupported => {
+                "VERIFICATION_RESULT_REGION_NOT_SUPPORTED"
+            }
+            VerificationResult::TooManyAttempts => "VERIFICATION_RESULT_TOO_MANY_ATTEMPTS",
+            VerificationResult::InvalidUserName => "VERIFICATION_RESULT_INVALID_USER_NAME",
+            VerificationResult::InvalidEncryption => {
+                "VERIFICATION_RESULT_INVALID_ENCRYPTION"
+            }
         }
     }
     /// Creates an enum from field names used in the ProtoBuf definition.
@@ -150,6 +356,150 @@ impl VerificationResult {
             "VERIFICATION_RESULT_MISSING_DATA" => Some(Self::MissingData),
             "VERIFICATION_RESULT_FAILED" => Some(Self::Failed),
             "VERIFICATION_RESULT_INVALID_SIGNATURE" => Some(Self::InvalidSignature),
+            "VERIFICATION_RESULT_INVALID_SESSION" => Some(Self::InvalidSession),
+            "VERIFICATION_RESULT_STALE_REQUEST" => Some(Self::StaleRequest),
+            "VERIFICATION_RESULT_DUPLICATE_REQUEST" => Some(Self::DuplicateRequest),
+            "VERIFICATION_RESULT_REGION_NOT_SUPPORTED" => {
+                Some(Self::RegionNotSupported)
+            }
+            "VERIFICATION_RESULT_TOO_MANY_ATTEMPTS" => Some(Self::TooManyAttempts),
+            "VERIFICATION_RESULT_INVALID_USER_NAME" => Some(Self::InvalidUserName),
+            "VERIFICATION_RESULT_INVALID_ENCRYPTION" => Some(Self::InvalidEncryption),
+            _ => None,
+        }
+    }
+}
+/// Verification code delivery channels
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum VerificationChannel {
+    Unspecified = 0,
+    Sms = 1,
+    Voice = 2,
+    Whatsapp = 3,
+}
+impl VerificationChannel {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            VerificationChannel::Unspecified => "VERIFICATION_CHANNEL_UNSPECIFIED",
+            VerificationChannel::Sms => "VERIFICATION_CHANNEL_SMS",
+            VerificationChannel::Voice => "VERIFICATION_CHANNEL_VOICE",
+            VerificationChannel::Whatsapp => "VERIFICATION_CHANNEL_WHATSAPP",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "VERIFICATION_CHANNEL_UNSPECIFIED" => Some(Self::Unspecified),
+            "VERIFICATION_CHANNEL_SMS" => Some(Self::Sms),
+            "VERIFICATION_CHANNEL_VOICE" => Some(Self::Voice),
+            "VERIFICATION_CHANNEL_WHATSAPP" => Some(Self::Whatsapp),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum EvidenceStatus {
+    Unspecified = 0,
+    /// signed by this verifier and not expired
+    Valid = 1,
+    /// signed by this verifier and expired
+    Expired = 2,
+    /// not signed by a current or past key of this verifier
+    InvalidSignature = 3,
+    /// evidence can't be decoded
+    Malformed = 4,
+}
+impl EvidenceStatus {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            EvidenceStatus::Unspecified => "EVIDENCE_STATUS_UNSPECIFIED",
+            EvidenceStatus::Valid => "EVIDENCE_STATUS_VALID",
+            EvidenceStatus::Expired => "EVIDENCE_STATUS_EXPIRED",
+            EvidenceStatus::InvalidSignature => "EVIDENCE_STATUS_INVALID_SIGNATURE",
+            EvidenceStatus::Malformed => "EVIDENCE_STATUS_MALFORMED",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "EVIDENCE_STATUS_UNSPECIFIED" => Some(Self::Unspecified),
+            "EVIDENCE_STATUS_VALID" => Some(Self::Valid),
+            "EVIDENCE_STATUS_EXPIRED" => Some(Self::Expired),
+            "EVIDENCE_STATUS_INVALID_SIGNATURE" => Some(Self::InvalidSignature),
+            "EVIDENCE_STATUS_MALFORMED" => Some(Self::Malformed),
+            _ => None,
+        }
+    }
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum EvidenceFormat {
+    Unspecified = 0,
+    /// ed25519 signed scale encoded evidence with a blake2-512 phone number hash
+    ScaleEd25519V1 = 1,
+    /// ed25519 signed scale encoded evidence with a phone number hash scheme tag
+    ScaleEd25519V2 = 2,
+}
+impl EvidenceFormat {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            EvidenceFormat::Unspecified => "EVIDENCE_FORMAT_UNSPECIFIED",
+            EvidenceFormat::ScaleEd25519V1 => "EVIDENCE_FORMAT_SCALE_ED25519_V1",
+            EvidenceFormat::ScaleEd25519V2 => "EVIDENCE_FORMAT_SCALE_ED25519_V2",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "EVIDENCE_FORMAT_UNSPECIFIED" => Some(Self::Unspecified),
+            "EVIDENCE_FORMAT_SCALE_ED25519_V1" => Some(Self::ScaleEd25519V1),
+            "EVIDENCE_FORMAT_SCALE_ED25519_V2" => Some(Self::ScaleEd25519V2),
+            _ => None,
+        }
+    }
+}
+/// Phone number hash schemes. Values match the scheme tag of scale encoded evidence
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, ::prost::Enumeration)]
+#[repr(i32)]
+pub enum PhoneNumberHashScheme {
+    Unspecified = 0,
+    /// unkeyed blake2-512 hash of the E.164 number
+    Blake2512 = 1,
+    /// hmac-sha512 of the E.164 number keyed with a verifier held pepper
+    HmacSha512 = 2,
+}
+impl PhoneNumberHashScheme {
+    /// String value of the enum field names used in the ProtoBuf definition.
+    ///
+    /// The values are not transformed in any way and thus are considered stable
+    /// (if the ProtoBuf definition does not change) and safe for programmatic use.
+    pub fn as_str_name(&self) -> &'static str {
+        match self {
+            PhoneNumberHashScheme::Unspecified => "PHONE_NUMBER_HASH_SCHEME_UNSPECIFIED",
+            PhoneNumberHashScheme::Blake2512 => "PHONE_NUMBER_HASH_SCHEME_BLAKE2_512",
+            PhoneNumberHashScheme::HmacSha512 => "PHONE_NUMBER_HASH_SCHEME_HMAC_SHA512",
+        }
+    }
+    /// Creates an enum from field names used in the ProtoBuf definition.
+    pub fn from_str_name(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "PHONE_NUMBER_HASH_SCHEME_UNSPECIFIED" => Some(Self::Unspecified),
+            "PHONE_NUMBER_HASH_SCHEME_BLAKE2_512" => Some(Self::Blake2512),
+            "PHONE_NUMBER_HASH_SCHEME_HMAC_SHA512" => Some(Self::HmacSha512),
             _ => None,
         }
     }
@@ -224,7 +574,7 @@ pub mod verifier_service_client {
             self.inner = self.inner.accept_compressed(encoding);
             self
         }
-        /// Send verification code to the user's mobile number via whatsapp
+        /// Send verification code to the user's mobile number via the requested channel
         pub async fn send_verification_code(
             &mut self,
             request: impl tonic::IntoRequest<super::SendVerificationCodeRequest>,
@@ -267,6 +617,66 @@ pub mod verifier_service_client {
             );
             self.inner.unary(request.into_request(), path, codec).await
         }
+        /// Check evidence previously issued by this verifier
+        pub async fn verify_evidence(
+            &mut self,
+            request: impl tonic::IntoRequest<super::VerifyEvidenceRequest>,
+        ) -> Result<tonic::Response<super::VerifyEvidenceResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.verifier.VerifierService/VerifyEvidence",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Get the verifier's identity, keys and capabilities
+        pub async fn get_verifier_info(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetVerifierInfoRequest>,
+        ) -> Result<tonic::Response<super::GetVerifierInfoResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.verifier.VerifierService/GetVerifierInfo",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
+        /// Get the verifier's current signed pre-key bundle clients encrypt requests to
+        pub async fn get_pre_key_bundle(
+            &mut self,
+            request: impl tonic::IntoRequest<super::GetPreKeyBundleRequest>,
+        ) -> Result<tonic::Response<super::GetPreKeyBundleResponse>, tonic::Status> {
+            self.inner
+                .ready()
+                .await
+                .map_err(|e| {
+                    tonic::Status::new(
+                        tonic::Code::Unknown,
+                        format!("Service was not ready: {}", e.into()),
+                    )
+                })?;
+            let codec = tonic::codec::ProstCodec::default();
+            let path = http::uri::PathAndQuery::from_static(
+                "/karma_coin.verifier.VerifierService/GetPreKeyBundle",
+            );
+            self.inner.unary(request.into_request(), path, codec).await
+        }
     }
 }
 /// Generated server implementations.
@@ -276,7 +686,7 @@ pub mod verifier_service_server {
     /// Generated trait containing gRPC methods that should be implemented for use with VerifierServiceServer.
     #[async_trait]
     pub trait VerifierService: Send + Sync + 'static {
-        /// Send verification code to the user's mobile number via whatsapp
+        /// Send verification code to the user's mobile number via the requested channel
         async fn send_verification_code(
             &self,
             request: tonic::Request<super::SendVerificationCodeRequest>,
@@ -286,6 +696,21 @@ pub mod verifier_service_server {
             &self,
             request: tonic::Request<super::VerifyNumberRequest>,
         ) -> Result<tonic::Response<super::VerifyNumberResponse>, tonic::Status>;
+        /// Check evidence previously issued by this verifier
+        async fn verify_evidence(
+            &self,
+            request: tonic::Request<super::VerifyEvidenceRequest>,
+        ) -> Result<tonic::Response<super::VerifyEvidenceResponse>, tonic::Status>;
+        /// Get the verifier's identity, keys and capabilities
+        async fn get_verifier_info(
+            &self,
+            request: tonic::Request<super::GetVerifierInfoRequest>,
+        ) -> Result<tonic::Response<super::GetVerifierInfoResponse>, tonic::Status>;
+        /// Get the verifier's current signed pre-key bundle clients encrypt requests to
+        async fn get_pre_key_bundle(
+            &self,
+            request: tonic::Request<super::GetPreKeyBundleRequest>,
+        ) -> Result<tonic::Response<super::GetPreKeyBundleResponse>, tonic::Status>;
     }
     /// mobile phone numbers verifier api service
     #[derive(Debug)]
@@ -427,6 +852,126 @@ pub mod verifier_service_server {
                     };
                     Box::pin(fut)
                 }
+                "/karma_coin.verifier.VerifierService/VerifyEvidence" => {
+                    #[allow(non_camel_case_types)]
+                    struct VerifyEvidenceSvc<T: VerifierService>(pub Arc<T>);
+                    impl<
+                        T: VerifierService,
+                    > tonic::server::UnaryService<super::VerifyEvidenceRequest>
+                    for VerifyEvidenceSvc<T> {
+                        type Response = super::VerifyEvidenceResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::VerifyEvidenceRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).verify_evidence(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = VerifyEvidenceSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/karma_coin.verifier.VerifierService/GetVerifierInfo" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetVerifierInfoSvc<T: VerifierService>(pub Arc<T>);
+                    impl<
+                        T: VerifierService,
+                    > tonic::server::UnaryService<super::GetVerifierInfoRequest>
+                    for GetVerifierInfoSvc<T> {
+                        type Response = super::GetVerifierInfoResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetVerifierInfoRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_verifier_info(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetVerifierInfoSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
+                "/karma_coin.verifier.VerifierService/GetPreKeyBundle" => {
+                    #[allow(non_camel_case_types)]
+                    struct GetPreKeyBundleSvc<T: VerifierService>(pub Arc<T>);
+                    impl<
+                        T: VerifierService,
+                    > tonic::server::UnaryService<super::GetPreKeyBundleRequest>
+                    for GetPreKeyBundleSvc<T> {
+                        type Response = super::GetPreKeyBundleResponse;
+                        type Future = BoxFuture<
+                            tonic::Response<Self::Response>,
+                            tonic::Status,
+                        >;
+                        fn call(
+                            &mut self,
+                            request: tonic::Request<super::GetPreKeyBundleRequest>,
+                        ) -> Self::Future {
+                            let inner = self.0.clone();
+                            let fut = async move {
+                                (*inner).get_pre_key_bundle(request).await
+                            };
+                            Box::pin(fut)
+                        }
+                    }
+                    let accept_compression_encodings = self.accept_compression_encodings;
+                    let send_compression_encodings = self.send_compression_encodings;
+                    let inner = self.inner.clone();
+                    let fut = async move {
+                        let inner = inner.0;
+                        let method = GetPreKeyBundleSvc(inner);
+                        let codec = tonic::codec::ProstCodec::default();
+                        let mut grpc = tonic::server::Grpc::new(codec)
+                            .apply_compression_config(
+                                accept_compression_encodings,
+                                send_compression_encodings,
+                            );
+                        let res = grpc.unary(method, req).await;
+                        Ok(res)
+                    };
+                    Box::pin(fut)
+                }
                 _ => {
                     Box::pin(async move {
                         Ok(
@@ -3,4 +3,5 @@
 //
 
 pub mod google_protobuf;
+pub mod karma_coin_signer;
 pub mod karma_coin_verifier;
@@ -4,8 +4,9 @@
 
 use anyhow::{anyhow, Result};
 use config::builder::DefaultState;
-use config::{Config, ConfigBuilder};
+use config::{Config, ConfigBuilder, Value, ValueKind};
 use log::*;
+use serde::de::DeserializeOwned;
 use std::path::Path;
 use xactor::*;
 
@@ -14,6 +15,127 @@ pub const VERIFIER_NAME: &str = "verifier.name";
 pub const VERIFIER_ID_PRIVATE_KEY: &str = "verifier.private_key";
 pub const VERIFIER_ID_PUBLIC_KEY: &str = "verifier.public_key";
 
+// Verifier signing keys. The single key used when no keys are set is unlocked from keystore,
+// or derived from key_mnemonic and key_password when no keystore is set
+pub const VERIFIER_KEYSTORE_CONFIG_KEY: &str = "verifier.keystore";
+pub const VERIFIER_KEYSTORE_PASSWORD_ENV_CONFIG_KEY: &str = "verifier.keystore_password_env";
+pub const VERIFIER_KEY_MNEMONIC_CONFIG_KEY: &str = "verifier.key_mnemonic";
+pub const VERIFIER_KEY_PASSWORD_CONFIG_KEY: &str = "verifier.key_password";
+pub const VERIFIER_KEYS_CONFIG_KEY: &str = "verifier.keys";
+
+// ss58 public keys the verifier used in the past - evidence signed with them is still accepted by VerifyEvidence
+pub const VERIFIER_PAST_PUBLIC_KEYS_CONFIG_KEY: &str = "verifier.past_public_keys";
+
+// Verification provider used to send and check codes
+pub const VERIFIER_PROVIDER_CONFIG_KEY: &str = "verifier.provider";
+pub const DEFAULT_VERIFIER_PROVIDER: &str = "twilio";
+// Ordered providers and channels to fall back to when sending with the primary provider fails
+pub const VERIFIER_FAILOVER_CONFIG_KEY: &str = "verifier.failover";
+
+// Time a verification session may be completed in after the code was sent
+pub const VERIFIER_SESSION_TTL_SECS_CONFIG_KEY: &str = "verifier.session_ttl_secs";
+pub const DEFAULT_VERIFIER_SESSION_TTL_SECS: i64 = 600;
+
+// Wrong codes which may be checked in a verification session before it is locked
+pub const VERIFIER_MAX_CODE_ATTEMPTS_CONFIG_KEY: &str = "verifier.max_code_attempts";
+pub const DEFAULT_VERIFIER_MAX_CODE_ATTEMPTS: i64 = 5;
+
+// Tokens which verify numbers without a code. bypass_token is the legacy single token
+pub const VERIFIER_BYPASS_TOKEN_CONFIG_KEY: &str = "verifier.bypass_token";
+pub const VERIFIER_BYPASS_TOKENS_CONFIG_KEY: &str = "verifier.bypass_tokens";
+
+// Optional ss58 network prefix account ids of verify number requests must have
+pub const VERIFIER_SS58_PREFIX_CONFIG_KEY: &str = "verifier.ss58_prefix";
+
+// User name policy
+pub const USERNAME_MIN_LENGTH_CONFIG_KEY: &str = "verifier.username.min_length";
+pub const USERNAME_MAX_LENGTH_CONFIG_KEY: &str = "verifier.username.max_length";
+pub const USERNAME_ALLOWED_CHARACTERS_CONFIG_KEY: &str = "verifier.username.allowed_characters";
+pub const USERNAME_RESERVED_CONFIG_KEY: &str = "verifier.username.reserved";
+pub const DEFAULT_USERNAME_MIN_LENGTH: i64 = 3;
+pub const DEFAULT_USERNAME_MAX_LENGTH: i64 = 24;
+pub const DEFAULT_USERNAME_ALLOWED_CHARACTERS: [&str; 5] =
+    ["letters", "digits", "underscore", "hyphen", "period"];
+pub const DEFAULT_USERNAME_RESERVED: [&str; 6] = [
+    "admin",
+    "karma",
+    "karmacoin",
+    "verifier",
+    "support",
+    "system",
+];
+
+// Time verification evidence is valid for after it was issued
+pub const VERIFIER_EVIDENCE_TTL_SECS_CONFIG_KEY: &str = "verifier.evidence_ttl_secs";
+pub const DEFAULT_VERIFIER_EVIDENCE_TTL_SECS: i64 = 86400;
+
+// Scheme evidence phone number hashes are computed with - blake2_512 or hmac_sha512.
+// hmac_sha512 keys the hash with a hex encoded secret pepper of at least 32 bytes
+pub const VERIFIER_PHONE_NUMBER_HASH_SCHEME_CONFIG_KEY: &str = "verifier.phone_number_hash.scheme";
+pub const VERIFIER_PHONE_NUMBER_HASH_PEPPER_CONFIG_KEY: &str = "verifier.phone_number_hash.pepper";
+pub const DEFAULT_VERIFIER_PHONE_NUMBER_HASH_SCHEME: &str = "blake2_512";
+
+// X2DH pre-keys. A new pre-key bundle is published every rotation period and
+// replaced bundles still decrypt requests for the grace period.
+// Pre-keys are persisted to the store file encrypted with the password in the store_password_env env var
+pub const VERIFIER_PRE_KEY_ROTATION_SECS_CONFIG_KEY: &str = "verifier.pre_keys.rotation_secs";
+pub const VERIFIER_PRE_KEY_GRACE_SECS_CONFIG_KEY: &str = "verifier.pre_keys.grace_secs";
+pub const VERIFIER_PRE_KEY_STORE_CONFIG_KEY: &str = "verifier.pre_keys.store";
+pub const VERIFIER_PRE_KEY_STORE_PASSWORD_ENV_CONFIG_KEY: &str =
+    "verifier.pre_keys.store_password_env";
+pub const DEFAULT_VERIFIER_PRE_KEY_ROTATION_SECS: i64 = 86400;
+pub const DEFAULT_VERIFIER_PRE_KEY_GRACE_SECS: i64 = 3600;
+
+// Maximum difference between a verify number request's timestamp and the verifier's clock
+pub const VERIFIER_REQUEST_MAX_AGE_SECS_CONFIG_KEY: &str = "verifier.request_max_age_secs";
+pub const DEFAULT_VERIFIER_REQUEST_MAX_AGE_SECS: i64 = 300;
+
+// Country calling codes the verifier sends codes to. An empty allowlist allows all countries
+pub const VERIFIER_COUNTRY_ALLOWLIST_CONFIG_KEY: &str = "verifier.country_allowlist";
+pub const VERIFIER_COUNTRY_BLOCKLIST_CONFIG_KEY: &str = "verifier.country_blocklist";
+
+// Rate limits - `capacity` requests per `window_secs` for each phone number, account and client ip.
+// A capacity of 0 disables a limit
+pub const RATE_LIMIT_PHONE_NUMBER_CAPACITY_CONFIG_KEY: &str =
+    "verifier.rate_limits.phone_number.capacity";
+pub const RATE_LIMIT_PHONE_NUMBER_WINDOW_SECS_CONFIG_KEY: &str =
+    "verifier.rate_limits.phone_number.window_secs";
+pub const RATE_LIMIT_ACCOUNT_CAPACITY_CONFIG_KEY: &str = "verifier.rate_limits.account.capacity";
+pub const RATE_LIMIT_ACCOUNT_WINDOW_SECS_CONFIG_KEY: &str =
+    "verifier.rate_limits.account.window_secs";
+pub const RATE_LIMIT_CLIENT_IP_CAPACITY_CONFIG_KEY: &str =
+    "verifier.rate_limits.client_ip.capacity";
+pub const RATE_LIMIT_CLIENT_IP_WINDOW_SECS_CONFIG_KEY: &str =
+    "verifier.rate_limits.client_ip.window_secs";
+pub const DEFAULT_RATE_LIMIT_PHONE_NUMBER_CAPACITY: i64 = 5;
+pub const DEFAULT_RATE_LIMIT_PHONE_NUMBER_WINDOW_SECS: i64 = 3600;
+pub const DEFAULT_RATE_LIMIT_ACCOUNT_CAPACITY: i64 = 10;
+pub const DEFAULT_RATE_LIMIT_ACCOUNT_WINDOW_SECS: i64 = 3600;
+pub const DEFAULT_RATE_LIMIT_CLIENT_IP_CAPACITY: i64 = 30;
+pub const DEFAULT_RATE_LIMIT_CLIENT_IP_WINDOW_SECS: i64 = 60;
+
+// Code delivery channels
+pub const VERIFIER_DEFAULT_CHANNEL_CONFIG_KEY: &str = "verifier.default_channel";
+pub const VERIFIER_ENABLED_CHANNELS_CONFIG_KEY: &str = "verifier.enabled_channels";
+pub const DEFAULT_VERIFIER_CHANNEL: &str = "whatsapp";
+pub const DEFAULT_VERIFIER_ENABLED_CHANNELS: [&str; 3] = ["whatsapp", "sms", "voice"];
+
+// Twilio verify api credentials
+pub const TWILIO_ACCOUNT_SID_CONFIG_KEY: &str = "twilio.account_sid";
+pub const TWILIO_SERVICE_ID_CONFIG_KEY: &str = "twilio.service_id";
+pub const TWILIO_AUTH_TOKEN_CONFIG_KEY: &str = "twilio.auth_token";
+pub const TWILIO_BASE_URL_CONFIG_KEY: &str = "twilio.base_url";
+pub const TWILIO_TIMEOUT_SECS_CONFIG_KEY: &str = "twilio.timeout_secs";
+pub const DEFAULT_TWILIO_BASE_URL: &str = "https://verify.twilio.com";
+pub const DEFAULT_TWILIO_TIMEOUT_SECS: i64 = 10;
+
+// Code issued by the mock verification provider
+pub const MOCK_PROVIDER_CODE_CONFIG_KEY: &str = "mock.code";
+pub const DEFAULT_MOCK_PROVIDER_CODE: &str = "123456";
+// The mock provider must be explicitly enabled so it can't be configured in production by mistake
+pub const MOCK_PROVIDER_ENABLED_CONFIG_KEY: &str = "mock.enabled";
+pub const DEFAULT_MOCK_PROVIDER_ENABLED: bool = false;
+
 pub const DEFAULT_GRPC_SERVER_PORT: i64 = 9080;
 pub const DEFAULT_GRPC_ADMIN_PORT: i64 = 9888;
 pub const DEFAULT_START_GRPC_SERVER: bool = true;
@@ -46,6 +168,108 @@ impl ServerConfigService {
             // we always want to have a peer name - even a generic one
             .set_default(SERVER_NAME_CONFIG_KEY, "Karmachain1.0")
             .unwrap()
+            .set_default(VERIFIER_PROVIDER_CONFIG_KEY, DEFAULT_VERIFIER_PROVIDER)
+            .unwrap()
+            .set_default(MOCK_PROVIDER_CODE_CONFIG_KEY, DEFAULT_MOCK_PROVIDER_CODE)
+            .unwrap()
+            .set_default(
+                MOCK_PROVIDER_ENABLED_CONFIG_KEY,
+                DEFAULT_MOCK_PROVIDER_ENABLED,
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_DEFAULT_CHANNEL_CONFIG_KEY,
+                DEFAULT_VERIFIER_CHANNEL,
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_ENABLED_CHANNELS_CONFIG_KEY,
+                DEFAULT_VERIFIER_ENABLED_CHANNELS.to_vec(),
+            )
+            .unwrap()
+            .set_default(TWILIO_BASE_URL_CONFIG_KEY, DEFAULT_TWILIO_BASE_URL)
+            .unwrap()
+            .set_default(TWILIO_TIMEOUT_SECS_CONFIG_KEY, DEFAULT_TWILIO_TIMEOUT_SECS)
+            .unwrap()
+            .set_default(
+                VERIFIER_SESSION_TTL_SECS_CONFIG_KEY,
+                DEFAULT_VERIFIER_SESSION_TTL_SECS,
+            )
+            .unwrap()
+            .set_default(USERNAME_MIN_LENGTH_CONFIG_KEY, DEFAULT_USERNAME_MIN_LENGTH)
+            .unwrap()
+            .set_default(USERNAME_MAX_LENGTH_CONFIG_KEY, DEFAULT_USERNAME_MAX_LENGTH)
+            .unwrap()
+            .set_default(
+                USERNAME_ALLOWED_CHARACTERS_CONFIG_KEY,
+                DEFAULT_USERNAME_ALLOWED_CHARACTERS.to_vec(),
+            )
+            .unwrap()
+            .set_default(
+                USERNAME_RESERVED_CONFIG_KEY,
+                DEFAULT_USERNAME_RESERVED.to_vec(),
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_EVIDENCE_TTL_SECS_CONFIG_KEY,
+                DEFAULT_VERIFIER_EVIDENCE_TTL_SECS,
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_PHONE_NUMBER_HASH_SCHEME_CONFIG_KEY,
+                DEFAULT_VERIFIER_PHONE_NUMBER_HASH_SCHEME,
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_PRE_KEY_ROTATION_SECS_CONFIG_KEY,
+                DEFAULT_VERIFIER_PRE_KEY_ROTATION_SECS,
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_PRE_KEY_GRACE_SECS_CONFIG_KEY,
+                DEFAULT_VERIFIER_PRE_KEY_GRACE_SECS,
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_MAX_CODE_ATTEMPTS_CONFIG_KEY,
+                DEFAULT_VERIFIER_MAX_CODE_ATTEMPTS,
+            )
+            .unwrap()
+            .set_default(
+                VERIFIER_REQUEST_MAX_AGE_SECS_CONFIG_KEY,
+                DEFAULT_VERIFIER_REQUEST_MAX_AGE_SECS,
+            )
+            .unwrap()
+            .set_default(
+                RATE_LIMIT_PHONE_NUMBER_CAPACITY_CONFIG_KEY,
+                DEFAULT_RATE_LIMIT_PHONE_NUMBER_CAPACITY,
+            )
+            .unwrap()
+            .set_default(
+                RATE_LIMIT_PHONE_NUMBER_WINDOW_SECS_CONFIG_KEY,
+                DEFAULT_RATE_LIMIT_PHONE_NUMBER_WINDOW_SECS,
+            )
+            .unwrap()
+            .set_default(
+                RATE_LIMIT_ACCOUNT_CAPACITY_CONFIG_KEY,
+                DEFAULT_RATE_LIMIT_ACCOUNT_CAPACITY,
+            )
+            .unwrap()
+            .set_default(
+                RATE_LIMIT_ACCOUNT_WINDOW_SECS_CONFIG_KEY,
+                DEFAULT_RATE_LIMIT_ACCOUNT_WINDOW_SECS,
+            )
+            .unwrap()
+            .set_default(
+                RATE_LIMIT_CLIENT_IP_CAPACITY_CONFIG_KEY,
+                DEFAULT_RATE_LIMIT_CLIENT_IP_CAPACITY,
+            )
+            .unwrap()
+            .set_default(
+                RATE_LIMIT_CLIENT_IP_WINDOW_SECS_CONFIG_KEY,
+                DEFAULT_RATE_LIMIT_CLIENT_IP_WINDOW_SECS,
+            )
+            .unwrap()
     }
 }
 
@@ -112,6 +336,19 @@ impl ServerConfigService {
         Ok(res)
     }
 
+    /// Returns a structured config value such as a list or a table deserialized to T
+    pub async fn get_typed<T: DeserializeOwned>(key: String) -> Result<Option<T>> {
+        let config = ServerConfigService::from_registry().await?;
+        match config.call(GetRawValue(key.clone())).await? {
+            Some(value) => {
+                Ok(Some(value.try_deserialize().map_err(|e| {
+                    anyhow!("invalid config value for key {}: {:?}", key, e)
+                })?))
+            }
+            None => Ok(None),
+        }
+    }
+
     pub async fn set(key: String, value: String) -> Result<()> {
         let config = ServerConfigService::from_registry().await?;
         config.call(SetValue { key, value }).await?
@@ -203,6 +440,49 @@ impl Handler<SetConfigFile> for ServerConfigService {
     }
 }
 
+/// Reloads the values of the provided keys from the config file which was set with SetConfigFile.
+/// Other values are not reloaded as services read them once when they start. Keys which were removed from the
+/// file are unset.
+#[message(result = "Result<()>")]
+pub struct ReloadConfig {
+    pub keys: Vec<String>,
+}
+
+#[async_trait::async_trait]
+impl Handler<ReloadConfig> for ServerConfigService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: ReloadConfig) -> Result<()> {
+        let config_file = self
+            .config_file
+            .clone()
+            .ok_or_else(|| anyhow!("no config file to reload"))?;
+
+        // keep the current config when the file is invalid
+        let file_config = self
+            .get_default_builder()
+            .add_source(config::File::with_name(&config_file))
+            .build()
+            .map_err(|e| anyhow!("failed to reload config file {}: {:?}", config_file, e))?;
+
+        let mut builder = ConfigBuilder::<DefaultState>::default().add_source(self.config.clone());
+        for key in msg.keys.iter() {
+            let value = file_config
+                .get::<Value>(key)
+                .unwrap_or_else(|_| Value::new(None, ValueKind::Nil));
+            builder = builder
+                .set_override(key.as_str(), value)
+                .map_err(|e| anyhow!("failed to reload config key {}: {:?}", key, e))?;
+        }
+
+        self.config = builder
+            .build()
+            .map_err(|e| anyhow!("failed to reload config file {}: {:?}", config_file, e))?;
+
+        info!("reloaded {:?} from config file {:?}", msg.keys, config_file);
+
+        Ok(())
+    }
+}
+
 #[message(result = "Option<bool>")]
 pub struct GetBool(pub String);
 
@@ -243,6 +523,24 @@ impl Handler<GetValue> for ServerConfigService {
     }
 }
 
+#[message(result = "Option<Value>")]
+pub struct GetRawValue(pub String);
+
+#[async_trait::async_trait]
+impl Handler<GetRawValue> for ServerConfigService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: GetRawValue) -> Option<Value> {
+        match self.config.get::<Value>(&msg.0.as_str()) {
+            // values unset by ReloadConfig
+            Ok(Value {
+                kind: ValueKind::Nil,
+                ..
+            }) => None,
+            Ok(res) => Some(res),
+            Err(_) => None,
+        }
+    }
+}
+
 #[message(result = "Result<()>")]
 pub struct SetValue {
     pub key: String,
@@ -10,12 +10,25 @@ use crate::hmacer::Hmacer;
 use crate::kdfer::Kdfer;
 use anyhow::{anyhow, Result};
 use bytes::Bytes;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+
+/// Version of ciphertexts encrypted with XChaCha20-Poly1305 and a random nonce
+const XCHACHA20_POLY1305_VERSION: u8 = 1;
+const XCHACHA20_POLY1305_NONCE_LEN: usize = 24;
+const POLY1305_TAG_LEN: usize = 16;
+
+/// HKDF salt of the XChaCha20-Poly1305 key - separates it from legacy cipher keys
+const KEY_DERIVATION_SALT: &[u8] = b"karmacoin aead xchacha20poly1305";
 
 const HMAC_WIDTH: usize = 64; // 512 bits
 
 /// AEAD - Authenticated Encryption with Associated Data.
+/// Messages are encrypted with XChaCha20-Poly1305 under a key derived from the cipher's key and info,
+/// with a random nonce per message. Ciphertexts are framed as version (1 byte) || nonce (24 bytes) ||
+/// encrypted message || tag (16 bytes). The version byte is authenticated with the associated data.
 pub struct AeadCipher {
-    info: Bytes, // salt
+    info: Bytes, // key derivation info
     key: Bytes,  // 32 bytes key
     ad: Bytes,
 }
@@ -28,32 +41,61 @@ impl AeadCipher {
 
     /// Encrypt a message with this cipher
     pub fn encrypt(&self, plaintext: Bytes) -> Result<Bytes> {
-        // todo: look into using salt - pass salt as param.
-        let salt = [0; 64];
-        let mut keys = [0; 80];
+        let mut nonce = [0u8; XCHACHA20_POLY1305_NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| anyhow!("failed to create nonce: {}", e))?;
 
-        Kdfer::hkdf_sha512(&salt, &self.key.as_ref(), self.info.as_ref(), &mut keys)?;
-        let encryption_key = &keys[..32];
-        let authentication_key = &keys[32..64];
-        let iv = &keys[64..];
+        let ciphertext = self
+            .xchacha20_poly1305()?
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: plaintext.as_ref(),
+                    aad: &self.versioned_ad(XCHACHA20_POLY1305_VERSION),
+                },
+            )
+            .map_err(|_| anyhow!("encryption failed"))?;
 
-        let mut cypher_text =
-            AesCypher::aes256_cbc_pkcs7_encrypt(encryption_key, iv, &plaintext.as_ref())?;
+        let mut message = Vec::with_capacity(1 + nonce.len() + ciphertext.len());
+        message.push(XCHACHA20_POLY1305_VERSION);
+        message.extend_from_slice(&nonce);
+        message.extend_from_slice(&ciphertext);
+        Ok(Bytes::from(message))
+    }
 
-        let mac = Hmacer::hmac_sha512_two_inputs(
-            authentication_key,
-            self.ad.as_ref(),
-            cypher_text.as_ref(),
-        )?;
+    /// Decrypt a message encrypted with this cipher
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+        let (version, message) = ciphertext
+            .split_first()
+            .ok_or_else(|| anyhow!("invalid ciphertext length"))?;
+
+        if *version != XCHACHA20_POLY1305_VERSION {
+            return Err(anyhow!("unsupported ciphertext version: {}", version));
+        }
 
-        cypher_text.append(mac.unprotected_as_bytes().to_vec().as_mut());
-        Ok(Bytes::from(cypher_text))
+        if message.len() < XCHACHA20_POLY1305_NONCE_LEN + POLY1305_TAG_LEN {
+            return Err(anyhow!("invalid ciphertext length"));
+        }
+        let (nonce, message) = message.split_at(XCHACHA20_POLY1305_NONCE_LEN);
+
+        self.xchacha20_poly1305()?
+            .decrypt(
+                XNonce::from_slice(nonce),
+                Payload {
+                    msg: message,
+                    aad: &self.versioned_ad(*version),
+                },
+            )
+            .map_err(|_| anyhow!("invalid message tag"))
     }
 
-    /// Decrypt a message with this cipher
-    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
-        // todo: use salt as param
+    /// Decrypt a message encrypted before ciphertexts were versioned, with AES-256-CBC and an HMAC-SHA512 tag.
+    /// The iv of these messages is derived from the key and info so this is only used to migrate stored
+    /// ciphertexts - encrypt them again with encrypt().
+    pub fn decrypt_legacy(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
         let msg_len = ciphertext.len();
+        if msg_len < HMAC_WIDTH {
+            return Err(anyhow!("invalid ciphertext length"));
+        }
         let enc_len = msg_len - HMAC_WIDTH;
 
         let salt = [0; 64];
@@ -77,6 +119,24 @@ impl AeadCipher {
 
         AesCypher::aes256_cbc_pkcs7_decrypt(encryption_key, iv, &ciphertext[..enc_len])
     }
+
+    /// Returns the XChaCha20-Poly1305 cipher keyed with a key derived from the cipher's key and info
+    fn xchacha20_poly1305(&self) -> Result<XChaCha20Poly1305> {
+        let mut key = [0u8; 32];
+        Kdfer::hkdf_sha512(
+            KEY_DERIVATION_SALT,
+            self.key.as_ref(),
+            self.info.as_ref(),
+            &mut key,
+        )?;
+
+        XChaCha20Poly1305::new_from_slice(&key).map_err(|_| anyhow!("invalid cipher key length"))
+    }
+
+    /// Returns the associated data authenticated with a message of the provided ciphertext version
+    fn versioned_ad(&self, version: u8) -> Vec<u8> {
+        [&[version], self.ad.as_ref()].concat()
+    }
 }
 
 #[cfg(test)]
@@ -85,27 +145,126 @@ mod tests {
 
     use rand_core::{OsRng, RngCore};
 
+    fn random_bytes(len: usize) -> Bytes {
+        let mut bytes = vec![0u8; len];
+        OsRng.fill_bytes(&mut bytes);
+        Bytes::from(bytes)
+    }
+
+    fn random_cipher() -> AeadCipher {
+        AeadCipher::new(random_bytes(32), random_bytes(32), random_bytes(32))
+    }
+
     #[test]
     fn test_encrypt_round_trip() {
-        let mut info = [0u8; 32];
-        OsRng.fill_bytes(&mut info);
-        let info_bytes = Bytes::from(info.to_vec());
+        let plaintext = random_bytes(512);
 
-        let mut ad = [0u8; 32];
-        OsRng.fill_bytes(&mut ad);
-        let ad_bytes = Bytes::from(ad.to_vec());
+        let cipher = random_cipher();
+        let cipher_text = cipher.encrypt(plaintext.clone()).unwrap();
+        assert_eq!(cipher_text[0], XCHACHA20_POLY1305_VERSION);
+        assert_eq!(
+            cipher_text.len(),
+            1 + XCHACHA20_POLY1305_NONCE_LEN + plaintext.len() + POLY1305_TAG_LEN
+        );
 
-        let mut plaintext = [0u8; 512];
-        OsRng.fill_bytes(&mut plaintext);
-        let plaintext_bytes = Bytes::from(plaintext.to_vec());
+        let round_tripped = cipher.decrypt(&cipher_text).unwrap();
+        assert_eq!(plaintext.to_vec(), round_tripped);
+    }
 
-        let mut shared_secret = [0u8; 32];
-        OsRng.fill_bytes(&mut shared_secret);
-        let key = Bytes::from(shared_secret.to_vec());
+    #[test]
+    fn test_encrypt_empty_message() {
+        let cipher = random_cipher();
+        let cipher_text = cipher.encrypt(Bytes::new()).unwrap();
+        assert!(cipher.decrypt(&cipher_text).unwrap().is_empty());
+    }
 
-        let cipher = AeadCipher::new(info_bytes, key, ad_bytes);
-        let cipher_text = cipher.encrypt(plaintext_bytes).unwrap();
-        let round_tripped = cipher.decrypt(&cipher_text).unwrap();
-        assert_eq!(plaintext.as_ref().to_vec(), round_tripped);
+    #[test]
+    fn test_encrypt_uses_random_nonces() {
+        let plaintext = random_bytes(64);
+
+        let cipher = random_cipher();
+        let cipher_text1 = cipher.encrypt(plaintext.clone()).unwrap();
+        let cipher_text2 = cipher.encrypt(plaintext.clone()).unwrap();
+        assert_ne!(
+            cipher_text1[1..1 + XCHACHA20_POLY1305_NONCE_LEN],
+            cipher_text2[1..1 + XCHACHA20_POLY1305_NONCE_LEN]
+        );
+        assert_ne!(cipher_text1, cipher_text2);
+
+        assert_eq!(cipher.decrypt(&cipher_text1).unwrap(), plaintext.to_vec());
+        assert_eq!(cipher.decrypt(&cipher_text2).unwrap(), plaintext.to_vec());
+    }
+
+    #[test]
+    fn test_decrypt_tampered_message() {
+        let cipher = random_cipher();
+        let cipher_text = cipher.encrypt(random_bytes(64)).unwrap();
+
+        // nonce, message and tag bytes are authenticated
+        for i in [1, 1 + XCHACHA20_POLY1305_NONCE_LEN, cipher_text.len() - 1] {
+            let mut tampered = cipher_text.to_vec();
+            tampered[i] ^= 1;
+            assert!(cipher.decrypt(&tampered).is_err());
+        }
+
+        assert!(cipher
+            .decrypt(&cipher_text[..cipher_text.len() - 1])
+            .is_err());
+    }
+
+    #[test]
+    fn test_decrypt_with_other_parameters() {
+        let cipher = random_cipher();
+        let cipher_text = cipher.encrypt(random_bytes(64)).unwrap();
+
+        let other_key = AeadCipher::new(cipher.info.clone(), random_bytes(32), cipher.ad.clone());
+        assert!(other_key.decrypt(&cipher_text).is_err());
+
+        let other_info = AeadCipher::new(random_bytes(32), cipher.key.clone(), cipher.ad.clone());
+        assert!(other_info.decrypt(&cipher_text).is_err());
+
+        let other_ad = AeadCipher::new(cipher.info.clone(), cipher.key.clone(), random_bytes(32));
+        assert!(other_ad.decrypt(&cipher_text).is_err());
+    }
+
+    #[test]
+    fn test_decrypt_invalid_framing() {
+        let cipher = random_cipher();
+        let cipher_text = cipher.encrypt(random_bytes(64)).unwrap();
+
+        let mut unknown_version = cipher_text.to_vec();
+        unknown_version[0] = XCHACHA20_POLY1305_VERSION + 1;
+        assert!(cipher.decrypt(&unknown_version).is_err());
+
+        assert!(cipher.decrypt(&[]).is_err());
+        assert!(cipher
+            .decrypt(&cipher_text[..1 + XCHACHA20_POLY1305_NONCE_LEN + POLY1305_TAG_LEN - 1])
+            .is_err());
+    }
+
+    #[test]
+    fn test_decrypt_legacy() {
+        // aes-256-cbc with an hmac-sha512 tag, as encrypted before ciphertexts were versioned
+        let cipher = AeadCipher::new(
+            Bytes::from_static(b"legacy test info"),
+            Bytes::from((0u8..32).collect::<Vec<u8>>()),
+            Bytes::from_static(b"legacy test ad"),
+        );
+        let cipher_text = hex::decode(
+            "99a152dac790a38468df7c9c945fff004a41ff60b5d6e3e67f05e001a7bddee49aca35f7fe545ea4ac239ff89befd574d2de0ebba2fc12e8a39f6620d61a1dd849463f3a5656095dec2f9adb7d4c461f4d70e4d3806da922d4a5b35d474f1396",
+        )
+        .unwrap();
+
+        assert_eq!(
+            cipher.decrypt_legacy(&cipher_text).unwrap(),
+            b"legacy aead cipher plaintext".to_vec()
+        );
+
+        let mut tampered = cipher_text.clone();
+        tampered[0] ^= 1;
+        assert!(cipher.decrypt_legacy(&tampered).is_err());
+
+        // legacy ciphertexts aren't versioned
+        assert!(cipher.decrypt(&cipher_text).is_err());
     }
 }
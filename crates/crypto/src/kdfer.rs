@@ -5,7 +5,7 @@
 use anyhow::{anyhow, Result};
 
 use bytebuffer::ByteBuffer;
-use orion::hazardous::kdf::hkdf;
+use orion::hazardous::kdf::{argon2i, hkdf};
 
 const SALT: &str = "upsetter secure messaging experiment";
 
@@ -58,4 +58,44 @@ impl Kdfer {
 
         Ok(())
     }
+
+    // Derive a 32 bytes key from a password and a salt of at least 8 bytes using argon2i
+    pub fn password_kdf(
+        password: &[u8],
+        salt: &[u8],
+        iterations: u32,
+        memory_kib: u32,
+    ) -> Result<[u8; 32]> {
+        let mut key = [0; 32];
+
+        argon2i::derive_key(password, salt, iterations, memory_kib, None, None, &mut key)
+            .map_err(|e| anyhow!("argon2i derive key failed: {}", e))?;
+
+        Ok(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_password_kdf() {
+        let key = Kdfer::password_kdf(b"password", b"saltsalt", 3, 64).unwrap();
+        assert_eq!(
+            key,
+            Kdfer::password_kdf(b"password", b"saltsalt", 3, 64).unwrap()
+        );
+        assert_ne!(
+            key,
+            Kdfer::password_kdf(b"password1", b"saltsalt", 3, 64).unwrap()
+        );
+        assert_ne!(
+            key,
+            Kdfer::password_kdf(b"password", b"saltsal1", 3, 64).unwrap()
+        );
+
+        // argon2 requires a salt of at least 8 bytes
+        assert!(Kdfer::password_kdf(b"password", b"salt", 3, 64).is_err());
+    }
 }
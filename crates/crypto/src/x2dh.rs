@@ -2,8 +2,10 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::aead_cypher::AeadCipher;
 use crate::kdfer::Kdfer;
 use crate::utils::{PublicKeyWrapper, StaticSecretWrapper};
+use anyhow::Result;
 use bytes::Bytes;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha512};
@@ -103,6 +105,28 @@ pub fn execute_bob(input: &ProtocolInputBob) -> ProtocolOutputBob {
     ProtocolOutputBob { shared_secret, ad }
 }
 
+/// HKDF info of the cipher of messages encrypted with an X2DH shared secret
+const MESSAGE_CIPHER_INFO: &[u8] = b"karmacoin x2dh message";
+
+/// Returns the cipher of messages sent by Alice to Bob after executing the protocol
+fn message_cipher(shared_secret: &[u8; 32], ad: &Bytes) -> AeadCipher {
+    AeadCipher::new(
+        Bytes::from_static(MESSAGE_CIPHER_INFO),
+        Bytes::copy_from_slice(shared_secret),
+        ad.clone(),
+    )
+}
+
+/// Encrypts a message to Bob with the output of Alice's protocol execution
+pub fn encrypt_alice(output: &ProtocolOutputAlice, plaintext: &[u8]) -> Result<Bytes> {
+    message_cipher(&output.shared_secret, &output.ad).encrypt(Bytes::copy_from_slice(plaintext))
+}
+
+/// Decrypts a message from Alice with the output of Bob's protocol execution
+pub fn decrypt_bob(output: &ProtocolOutputBob, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    message_cipher(&output.shared_secret, &output.ad).decrypt(ciphertext)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -161,4 +185,34 @@ mod tests {
             "dh failed - different AD computed"
         );
     }
+
+    #[test]
+    fn test_x2dh_message_round_trip() {
+        let bob_id_key_pair = ed25519_dalek::Keypair::generate(&mut rand_core::OsRng);
+        let bob_pre_key_private = x25519_dalek::StaticSecret::new(&mut rand_core::OsRng);
+        let bob_pre_key_public: PublicKey = (&bob_pre_key_private).into();
+
+        let output_alice = execute_alice(&ProtocolInputAlice {
+            ikb: bob_id_key_pair.public,
+            pkb: bob_pre_key_public,
+            b_bundle_id: 0,
+        });
+        let ciphertext = encrypt_alice(&output_alice, b"+14155552671").unwrap();
+
+        let output_bob = execute_bob(&ProtocolInputBob {
+            eka: output_alice.eka,
+            ikb_pair: bob_id_key_pair,
+            pkb_private: bob_pre_key_private,
+            b_bundle_id: 0,
+        });
+        assert_eq!(
+            decrypt_bob(&output_bob, &ciphertext).unwrap(),
+            b"+14155552671".to_vec()
+        );
+
+        let mut tampered = ciphertext.to_vec();
+        tampered[0] ^= 1;
+        assert!(decrypt_bob(&output_bob, &tampered).is_err());
+        assert!(decrypt_bob(&output_bob, &ciphertext[..16]).is_err());
+    }
 }
@@ -0,0 +1,173 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Commands to create, inspect and change the password of verifier keystore files.
+//! Passwords and mnemonics are read from env vars so they don't end up in shell history.
+
+use anyhow::{anyhow, Result};
+use clap::{App, Arg, ArgMatches, SubCommand};
+use server::keystore::{Keystore, DEFAULT_KEYSTORE_PASSWORD_ENV};
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use sp_core::ed25519::{Pair, Public};
+use sp_core::Pair as PairT;
+use std::path::Path;
+
+/// Env var holding the new password of a keystore file
+const DEFAULT_KEYSTORE_NEW_PASSWORD_ENV: &str = "VERIFIER_KEYSTORE_NEW_PASSWORD";
+
+/// Returns the keystore subcommand and its commands
+pub(crate) fn subcommand() -> App<'static, 'static> {
+    let file = Arg::with_name("file")
+        .help("Keystore file path")
+        .required(true)
+        .value_name("FILE");
+
+    let password_env = Arg::with_name("password-env")
+        .long("password-env")
+        .takes_value(true)
+        .value_name("ENV_VAR")
+        .default_value(DEFAULT_KEYSTORE_PASSWORD_ENV)
+        .help("Env var holding the keystore password");
+
+    SubCommand::with_name("keystore")
+        .about("Manage verifier keystore files")
+        .subcommand(
+            SubCommand::with_name("create")
+                .about("Creates a keystore file with a new key or a key derived from a mnemonic")
+                .arg(file.clone())
+                .arg(password_env.clone())
+                .arg(
+                    Arg::with_name("mnemonic-env")
+                        .long("mnemonic-env")
+                        .takes_value(true)
+                        .value_name("ENV_VAR")
+                        .help("Env var holding a mnemonic to derive the key from"),
+                )
+                .arg(
+                    Arg::with_name("mnemonic-password-env")
+                        .long("mnemonic-password-env")
+                        .takes_value(true)
+                        .value_name("ENV_VAR")
+                        .requires("mnemonic-env")
+                        .help("Env var holding the mnemonic's password"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("inspect")
+                .about("Prints the public key of a keystore file's key")
+                .arg(file.clone())
+                .arg(
+                    Arg::with_name("ss58-prefix")
+                        .long("ss58-prefix")
+                        .takes_value(true)
+                        .value_name("PREFIX")
+                        .help("Network prefix of the printed ss58 public key"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("change-password")
+                .about("Changes the password of a keystore file")
+                .arg(file)
+                .arg(password_env)
+                .arg(
+                    Arg::with_name("new-password-env")
+                        .long("new-password-env")
+                        .takes_value(true)
+                        .value_name("ENV_VAR")
+                        .default_value(DEFAULT_KEYSTORE_NEW_PASSWORD_ENV)
+                        .help("Env var holding the new keystore password"),
+                ),
+        )
+}
+
+/// Runs a keystore command
+pub(crate) fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        ("create", Some(matches)) => create(matches),
+        ("inspect", Some(matches)) => inspect(matches),
+        ("change-password", Some(matches)) => change_password(matches),
+        _ => Err(anyhow!("missing keystore command - see keystore --help")),
+    }
+}
+
+/// Returns a non-empty value of an env var
+fn env_value(env_var: &str) -> Result<String> {
+    match std::env::var(env_var) {
+        Ok(value) if !value.is_empty() => Ok(value),
+        _ => Err(anyhow!("env var {} is not set", env_var)),
+    }
+}
+
+fn print_public_key(public_key: &Public, ss58_prefix: Option<u16>) {
+    println!("public key raw: {}", hex::encode(public_key.0));
+    match ss58_prefix {
+        Some(prefix) => println!(
+            "public key ss58: {}",
+            public_key.to_ss58check_with_version(Ss58AddressFormat::custom(prefix))
+        ),
+        None => println!("public key ss58: {}", public_key.to_ss58check()),
+    }
+}
+
+fn create(matches: &ArgMatches) -> Result<()> {
+    let path = Path::new(matches.value_of("file").unwrap());
+    if path.exists() {
+        return Err(anyhow!("keystore file {} already exists", path.display()));
+    }
+
+    let password = env_value(matches.value_of("password-env").unwrap())?;
+
+    let pair = match matches.value_of("mnemonic-env") {
+        Some(mnemonic_env) => {
+            let mnemonic = env_value(mnemonic_env)?;
+            let mnemonic_password = matches
+                .value_of("mnemonic-password-env")
+                .map(env_value)
+                .transpose()?;
+            Pair::from_phrase(&mnemonic, mnemonic_password.as_deref())
+                .map_err(|e| anyhow!("invalid mnemonic: {:?}", e))?
+                .0
+        }
+        None => Pair::generate().0,
+    };
+
+    Keystore::create(&pair, &password)?.save(path)?;
+
+    println!("created keystore file {}", path.display());
+    print_public_key(&pair.public(), None);
+    Ok(())
+}
+
+fn inspect(matches: &ArgMatches) -> Result<()> {
+    let path = Path::new(matches.value_of("file").unwrap());
+    let ss58_prefix = matches
+        .value_of("ss58-prefix")
+        .map(|prefix| prefix.parse::<u16>())
+        .transpose()
+        .map_err(|e| anyhow!("invalid ss58 prefix: {}", e))?;
+
+    let keystore = Keystore::load(path)?;
+    print_public_key(&keystore.public_key()?, ss58_prefix);
+    if keystore.is_legacy() {
+        println!("keystore uses the legacy cipher - migrate it with change-password");
+    }
+    Ok(())
+}
+
+fn change_password(matches: &ArgMatches) -> Result<()> {
+    let path = Path::new(matches.value_of("file").unwrap());
+    let password = env_value(matches.value_of("password-env").unwrap())?;
+    let new_password = env_value(matches.value_of("new-password-env").unwrap())?;
+
+    let keystore = Keystore::load(path)?.change_password(&password, &new_password)?;
+
+    // replace the file only once the new keystore is fully written
+    let new_path = path.with_extension("new");
+    keystore.save(&new_path)?;
+    std::fs::rename(&new_path, path)
+        .map_err(|e| anyhow!("failed to replace keystore file {}: {}", path.display(), e))?;
+
+    println!("changed the password of keystore file {}", path.display());
+    Ok(())
+}
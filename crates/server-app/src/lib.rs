@@ -8,16 +8,18 @@ extern crate base;
 extern crate clap;
 
 use base::logging_service::{InitLogger, LoggingService};
-use server::server_service::{ServerService, Startup};
+use server::server_service::{Reload, ServerService, Startup};
 use tokio::signal;
 
 use clap::{App, Arg};
 
 use xactor::*;
 
+mod keystore_cli;
+
 // Start a client app - good for testability / integration testing
 pub async fn start() -> std::result::Result<(), Box<dyn std::error::Error>> {
-    let _matches = App::new("Karmachain 2.0 Verifier")
+    let matches = App::new("Karmachain 2.0 Verifier")
         .version("0.2.0")
         .author("AE  <a@karmaco.in>")
         .about("The coin for all of us")
@@ -30,8 +32,14 @@ pub async fn start() -> std::result::Result<(), Box<dyn std::error::Error>> {
                 .help("Sets a custom config file")
                 .takes_value(true),
         )
+        .subcommand(keystore_cli::subcommand())
         .get_matches();
 
+    if let ("keystore", Some(keystore_matches)) = matches.subcommand() {
+        keystore_cli::run(keystore_matches)?;
+        return Ok(());
+    }
+
     // Start app logger
     let logging = LoggingService::from_registry().await.unwrap();
     let _ = logging
@@ -50,6 +58,21 @@ pub async fn start() -> std::result::Result<(), Box<dyn std::error::Error>> {
     // test logging
     info!("Services started");
 
+    // reload the verifier keys config on SIGHUP to rotate keys
+    #[cfg(unix)]
+    spawn(async {
+        let mut hangup = signal::unix::signal(signal::unix::SignalKind::hangup())
+            .expect("failed to listen for hangup signal");
+        while hangup.recv().await.is_some() {
+            info!("reloading config via hangup signal...");
+            let result: Result<()> =
+                async { ServerService::from_registry().await?.call(Reload).await? }.await;
+            if let Err(e) = result {
+                error!("failed to reload config: {:?}", e);
+            }
+        }
+    });
+
     signal::ctrl_c()
         .await
         .expect("failed to listen for ctrl-c signal");
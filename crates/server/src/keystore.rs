@@ -0,0 +1,213 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Password protected keystore files holding a verifier ed25519 key seed, so the key doesn't have
+//! to be stored in plain text in the server config. The key is derived from the password with argon2i
+//! and the seed is encrypted with the crypto crate's AeadCipher, authenticating the public key.
+//! Version 1 keystores were encrypted with the legacy AES-CBC cipher. They can still be unlocked and are
+//! migrated to the current version by changing their password.
+
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use crypto::aead_cypher::AeadCipher;
+use crypto::kdfer::Kdfer;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sp_core::ed25519::{Pair, Public};
+use sp_core::Pair as PairT;
+use std::io::Write;
+use std::path::Path;
+
+/// Env var holding a keystore file's password when no other env var is configured
+pub const DEFAULT_KEYSTORE_PASSWORD_ENV: &str = "VERIFIER_KEYSTORE_PASSWORD";
+
+/// Current keystore file format version
+const KEYSTORE_VERSION: u32 = 2;
+
+/// Version of keystores encrypted with the legacy AES-CBC AeadCipher
+const LEGACY_KEYSTORE_VERSION: u32 = 1;
+
+/// Password key derivation algorithm
+const KDF_ALGORITHM: &str = "argon2i";
+const KDF_ITERATIONS: u32 = 3;
+const KDF_MEMORY_KIB: u32 = 64 * 1024;
+const KDF_SALT_LEN: usize = 16;
+
+/// Cipher info length - random per keystore so each encryption uses a unique cipher key and iv
+const CIPHER_INFO_LEN: usize = 32;
+
+/// Password key derivation parameters of a keystore
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub(crate) struct KeystoreKdf {
+    algorithm: String,
+    /// hex encoded salt
+    salt: String,
+    iterations: u32,
+    memory_kib: u32,
+}
+
+/// A keystore file's content
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Keystore {
+    version: u32,
+    /// hex encoded ed25519 public key of the stored key
+    public_key: String,
+    kdf: KeystoreKdf,
+    /// hex encoded cipher info
+    cipher_info: String,
+    /// hex encoded encrypted key seed
+    ciphertext: String,
+}
+
+impl Keystore {
+    /// Creates a keystore holding a key pair's seed protected by a password
+    pub fn create(pair: &Pair, password: &str) -> Result<Keystore> {
+        let mut cipher_info = [0u8; CIPHER_INFO_LEN];
+        OsRng.fill_bytes(&mut cipher_info);
+
+        let kdf = KeystoreKdf::generate();
+        let public_key = pair.public();
+        let cipher = Keystore::cipher(&kdf, password, &cipher_info, &public_key)?;
+        let ciphertext = cipher.encrypt(Bytes::from(pair.seed().to_vec()))?;
+
+        Ok(Keystore {
+            version: KEYSTORE_VERSION,
+            public_key: hex::encode(public_key.0),
+            kdf,
+            cipher_info: hex::encode(cipher_info),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Loads a keystore file
+    pub fn load(path: &Path) -> Result<Keystore> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read keystore file {:?}: {}", path, e))?;
+
+        let keystore: Keystore = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("invalid keystore file {:?}: {}", path, e))?;
+
+        if keystore.version != KEYSTORE_VERSION && keystore.version != LEGACY_KEYSTORE_VERSION {
+            return Err(anyhow!(
+                "unsupported keystore file version: {}",
+                keystore.version
+            ));
+        }
+
+        Ok(keystore)
+    }
+
+    /// Saves the keystore to a file which only its owner may read
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        write_private_file(path, content.as_bytes())
+            .map_err(|e| anyhow!("failed to write keystore file {:?}: {}", path, e))
+    }
+
+    /// Returns true if the keystore was encrypted with the legacy cipher and should be migrated
+    pub fn is_legacy(&self) -> bool {
+        self.version == LEGACY_KEYSTORE_VERSION
+    }
+
+    /// Returns the public key of the stored key. Available without the password
+    pub fn public_key(&self) -> Result<Public> {
+        let bytes = hex::decode(&self.public_key)?;
+        Public::try_from(bytes.as_slice()).map_err(|_| anyhow!("invalid keystore public key"))
+    }
+
+    /// Decrypts the stored key with the keystore's password
+    pub fn unlock(&self, password: &str) -> Result<Pair> {
+        let public_key = self.public_key()?;
+        let cipher = Keystore::cipher(
+            &self.kdf,
+            password,
+            &hex::decode(&self.cipher_info)?,
+            &public_key,
+        )?;
+
+        let ciphertext = hex::decode(&self.ciphertext)?;
+        let seed = match self.is_legacy() {
+            true => cipher.decrypt_legacy(&ciphertext),
+            false => cipher.decrypt(&ciphertext),
+        }
+        .map_err(|_| anyhow!("failed to unlock keystore - wrong password"))?;
+
+        let pair = Pair::from_seed_slice(&seed)
+            .map_err(|e| anyhow!("invalid keystore key seed: {:?}", e))?;
+
+        if pair.public() != public_key {
+            return Err(anyhow!("keystore key doesn't match its public key"));
+        }
+
+        Ok(pair)
+    }
+
+    /// Returns a keystore holding the same key protected by a new password.
+    /// The key is re-encrypted with a new salt and cipher info in the current keystore version.
+    pub fn change_password(&self, password: &str, new_password: &str) -> Result<Keystore> {
+        Keystore::create(&self.unlock(password)?, new_password)
+    }
+
+    /// Returns the cipher of a keystore's seed. The public key is authenticated with the seed.
+    fn cipher(
+        kdf: &KeystoreKdf,
+        password: &str,
+        cipher_info: &[u8],
+        public_key: &Public,
+    ) -> Result<AeadCipher> {
+        let key = kdf.derive_key(password)?;
+
+        Ok(AeadCipher::new(
+            Bytes::from(cipher_info.to_vec()),
+            Bytes::from(key.to_vec()),
+            Bytes::from(public_key.0.to_vec()),
+        ))
+    }
+}
+
+impl KeystoreKdf {
+    /// Returns the current key derivation parameters with a new random salt
+    pub(crate) fn generate() -> KeystoreKdf {
+        let mut salt = [0u8; KDF_SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+
+        KeystoreKdf {
+            algorithm: KDF_ALGORITHM.into(),
+            salt: hex::encode(salt),
+            iterations: KDF_ITERATIONS,
+            memory_kib: KDF_MEMORY_KIB,
+        }
+    }
+
+    /// Derives a cipher key from a password
+    pub(crate) fn derive_key(&self, password: &str) -> Result<[u8; 32]> {
+        if self.algorithm != KDF_ALGORITHM {
+            return Err(anyhow!(
+                "unsupported keystore kdf algorithm: {}",
+                self.algorithm
+            ));
+        }
+
+        Kdfer::password_kdf(
+            password.as_bytes(),
+            &hex::decode(&self.salt)?,
+            self.iterations,
+            self.memory_kib,
+        )
+    }
+}
+
+/// Writes a file which only its owner may read
+pub(crate) fn write_private_file(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)?.write_all(content)
+}
@@ -7,6 +7,10 @@ extern crate log;
 extern crate base;
 extern crate core;
 
+// used by server-app to manage verifier keystore files
+pub mod keystore;
+// persists the verifier's X2DH pre-keys across restarts
+pub mod pre_key_store;
 // used by server-app to start the server
 pub use services::server_service;
 mod services;
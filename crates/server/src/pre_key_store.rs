@@ -0,0 +1,142 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Password protected file persisting the verifier's X2DH identity key and unexpired pre-keys, so bundles
+//! clients fetched before a restart still decrypt their requests. The cipher key is derived from the password
+//! with the keystore's argon2i parameters once when the store is opened, and the keys are encrypted with the
+//! crypto crate's AeadCipher under a new random cipher info on every save.
+
+use crate::keystore::{write_private_file, KeystoreKdf};
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use crypto::aead_cypher::AeadCipher;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Env var holding the pre-key store password when no other env var is configured
+pub const DEFAULT_PRE_KEY_STORE_PASSWORD_ENV: &str = "VERIFIER_PRE_KEY_STORE_PASSWORD";
+
+/// Current pre-key store file format version
+const PRE_KEY_STORE_VERSION: u32 = 1;
+
+/// Cipher info length - random per save so each encryption uses a unique cipher key
+const CIPHER_INFO_LEN: usize = 32;
+
+/// Data authenticated with the stored keys - separates store ciphertexts from keystore ciphertexts
+const PRE_KEY_STORE_AD: &[u8] = b"karmacoin-pre-key-store";
+
+/// An x25519 pre-key and the data of its bundle
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoredPreKey {
+    pub secret: [u8; 32],
+    pub bundle_id: u64,
+    /// time the pre-key was created at in milliseconds
+    pub created_at: u64,
+    /// time the pre-key expires at in milliseconds
+    pub expires_at: u64,
+}
+
+/// The keys held in a pre-key store
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct StoredPreKeys {
+    /// secret of the X2DH ed25519 identity key
+    pub identity_secret: [u8; 32],
+    /// unexpired pre-keys by creation time
+    pub pre_keys: Vec<StoredPreKey>,
+}
+
+/// A pre-key store file's content
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct PreKeyStoreFile {
+    version: u32,
+    kdf: KeystoreKdf,
+    /// hex encoded cipher info
+    cipher_info: String,
+    /// hex encoded encrypted keys
+    ciphertext: String,
+}
+
+/// An opened pre-key store file
+pub struct PreKeyStore {
+    path: PathBuf,
+    kdf: KeystoreKdf,
+    /// cipher key derived from the store password
+    key: [u8; 32],
+}
+
+impl PreKeyStore {
+    /// Opens a pre-key store file and returns the keys it holds.
+    /// A new store without keys is returned when the file doesn't exist yet.
+    pub fn open(path: &Path, password: &str) -> Result<(PreKeyStore, Option<StoredPreKeys>)> {
+        if !path.exists() {
+            let kdf = KeystoreKdf::generate();
+            let key = kdf.derive_key(password)?;
+            let store = PreKeyStore {
+                path: path.to_path_buf(),
+                kdf,
+                key,
+            };
+            return Ok((store, None));
+        }
+
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("failed to read pre-key store file {:?}: {}", path, e))?;
+
+        let file: PreKeyStoreFile = serde_json::from_str(&content)
+            .map_err(|e| anyhow!("invalid pre-key store file {:?}: {}", path, e))?;
+
+        if file.version != PRE_KEY_STORE_VERSION {
+            return Err(anyhow!(
+                "unsupported pre-key store file version: {}",
+                file.version
+            ));
+        }
+
+        let key = file.kdf.derive_key(password)?;
+        let plaintext = PreKeyStore::cipher(&key, &hex::decode(&file.cipher_info)?)
+            .decrypt(&hex::decode(&file.ciphertext)?)
+            .map_err(|_| anyhow!("failed to open pre-key store - wrong password"))?;
+
+        let keys: StoredPreKeys = serde_json::from_slice(&plaintext)
+            .map_err(|e| anyhow!("invalid pre-key store keys: {}", e))?;
+
+        let store = PreKeyStore {
+            path: path.to_path_buf(),
+            kdf: file.kdf,
+            key,
+        };
+        Ok((store, Some(keys)))
+    }
+
+    /// Encrypts the keys and saves them to the store file, replacing the keys it held
+    pub fn save(&self, keys: &StoredPreKeys) -> Result<()> {
+        let mut cipher_info = [0u8; CIPHER_INFO_LEN];
+        OsRng.fill_bytes(&mut cipher_info);
+
+        let plaintext = serde_json::to_vec(keys)?;
+        let ciphertext =
+            PreKeyStore::cipher(&self.key, &cipher_info).encrypt(Bytes::from(plaintext))?;
+
+        let file = PreKeyStoreFile {
+            version: PRE_KEY_STORE_VERSION,
+            kdf: self.kdf.clone(),
+            cipher_info: hex::encode(cipher_info),
+            ciphertext: hex::encode(ciphertext),
+        };
+
+        let content = serde_json::to_string_pretty(&file)?;
+        write_private_file(&self.path, content.as_bytes())
+            .map_err(|e| anyhow!("failed to write pre-key store file {:?}: {}", self.path, e))
+    }
+
+    fn cipher(key: &[u8; 32], cipher_info: &[u8]) -> AeadCipher {
+        AeadCipher::new(
+            Bytes::from(cipher_info.to_vec()),
+            Bytes::from(key.to_vec()),
+            Bytes::from_static(PRE_KEY_STORE_AD),
+        )
+    }
+}
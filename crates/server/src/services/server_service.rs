@@ -2,14 +2,18 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
-use crate::services::verifier::verifier_service::VerifierService;
-use anyhow::Result;
-use base::server_config_service::SetConfigFile;
+use crate::services::verifier::key_ring::KEY_RING_CONFIG_KEYS;
+use crate::services::verifier::pre_key_service::PreKeyService;
+use crate::services::verifier::verifier_service::{ReloadKeys, VerifierService};
+use anyhow::{anyhow, Result};
+use base::server_config_service::{ReloadConfig, SetConfigFile};
 use base::server_config_service::{
     ServerConfigService, GRPC_SERVER_HOST_CONFIG_KEY, GRPC_SERVER_HOST_PORT_CONFIG_KEY,
     SERVER_NAME_CONFIG_KEY,
 };
 
+use std::net::SocketAddr;
+use tonic::transport::server::TcpIncoming;
 use tonic::transport::*;
 
 use base::karma_coin::karma_coin_verifier::verifier_service_server::VerifierServiceServer;
@@ -34,6 +38,8 @@ impl Actor for ServerService {
             })
             .await??;
 
+        PreKeyService::from_registry().await?;
+
         VerifierService::from_registry().await?;
 
         info!("started");
@@ -45,13 +51,13 @@ impl Service for ServerService {}
 
 ///////////////////////////
 
-#[message(result = "Result<()>")]
+#[message(result = "Result<SocketAddr>")]
 pub struct Startup;
 
-/// Start the grpc server
+/// Start the grpc server and return its bound address. A configured port of 0 binds any free port
 #[async_trait::async_trait]
 impl Handler<Startup> for ServerService {
-    async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: Startup) -> Result<()> {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: Startup) -> Result<SocketAddr> {
         info!("configuring server...");
 
         let server_name = ServerConfigService::get(SERVER_NAME_CONFIG_KEY.into())
@@ -66,24 +72,61 @@ impl Handler<Startup> for ServerService {
             .await?
             .unwrap() as u32;
 
-        self.start_grpc_server(port, host, server_name).await?;
+        let addr = self.start_grpc_server(port, host, server_name).await?;
+
+        info!("KC2 verifier grpc server started on: {}", addr);
+
+        Ok(addr)
+    }
+}
+
+#[message(result = "Result<()>")]
+pub struct Reload;
+
+/// Reload the verifier keys from the server config file, e.g. to rotate keys without a restart.
+/// Only the signing key config values are reloaded - changes to other values require a restart.
+#[async_trait::async_trait]
+impl Handler<Reload> for ServerService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: Reload) -> Result<()> {
+        info!("reloading verifier keys config...");
 
-        info!("KC2 verifier grpc server started");
+        ServerConfigService::from_registry()
+            .await?
+            .call(ReloadConfig {
+                keys: KEY_RING_CONFIG_KEYS.iter().map(|k| k.to_string()).collect(),
+            })
+            .await??;
+
+        VerifierService::from_registry()
+            .await?
+            .call(ReloadKeys)
+            .await??;
 
         Ok(())
     }
 }
 
 impl ServerService {
-    /// Starts the server's grpc services
-    async fn start_grpc_server(&self, port: u32, host: String, peer_name: String) -> Result<()> {
+    /// Starts the server's grpc services and returns the address the server is bound to
+    async fn start_grpc_server(
+        &self,
+        port: u32,
+        host: String,
+        peer_name: String,
+    ) -> Result<SocketAddr> {
         // setup grpc server and services
-        let grpc_server_addr = format!("{}:{}", host, port).parse()?;
+        let grpc_server_addr: SocketAddr = format!("{}:{}", host, port).parse()?;
         info!(
             "starting {} grpc server on: {}",
             peer_name, grpc_server_addr
         );
 
+        // bind before spawning the server so bind errors are returned and the bound port is known
+        let listener = tokio::net::TcpListener::bind(grpc_server_addr).await?;
+        let bound_addr = listener.local_addr()?;
+        let incoming = TcpIncoming::from_listener(listener, false, None)
+            .map_err(|e| anyhow!("failed to listen on {}: {}", bound_addr, e))?;
+
         let (mut verifier_health_reporter, verifier_health_service) =
             tonic_health::server::health_reporter();
 
@@ -106,7 +149,7 @@ impl ServerService {
                 .add_service(verifier_health_service)
                 .add_service(VerifierServiceServer::new(VerifierService::default()));
 
-            let res = router.serve(grpc_server_addr).await;
+            let res = router.serve_with_incoming(incoming).await;
 
             if res.is_err() {
                 info!("grpc server stopped due to error: {:?}", res.err().unwrap());
@@ -115,6 +158,6 @@ impl ServerService {
             }
         });
 
-        Ok(())
+        Ok(bound_addr)
     }
 }
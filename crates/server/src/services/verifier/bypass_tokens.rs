@@ -0,0 +1,187 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Bypass tokens let trusted clients, e.g. QA and app store reviewers, verify numbers without a code.
+//! Each token has a name for audit records, an optional expiry, usage quota and phone number prefix scope.
+//! Token use counts are kept in memory and start from zero when the verifier restarts.
+
+use anyhow::{anyhow, Result};
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_BYPASS_TOKENS_CONFIG_KEY, VERIFIER_BYPASS_TOKEN_CONFIG_KEY,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::fmt;
+use subtle::ConstantTimeEq;
+
+/// Name of the token configured with the legacy single bypass_token config value
+const LEGACY_TOKEN_NAME: &str = "legacy";
+
+/// Audit log target of bypass token use
+const AUDIT_LOG_TARGET: &str = "audit";
+
+/// A bypass token as defined in the server config
+#[derive(Deserialize, Debug, Clone)]
+struct BypassTokenConfig {
+    name: String,
+    token: String,
+    /// expiry time in milliseconds
+    expires_at: Option<u64>,
+    /// maximum number of verifications
+    max_uses: Option<u64>,
+    /// E.164 prefix of the numbers the token may verify, e.g. +1555
+    phone_number_prefix: Option<String>,
+}
+
+/// A bypass token and its use count
+struct BypassToken {
+    config: BypassTokenConfig,
+    uses: u64,
+}
+
+/// Reasons a provided bypass token is not accepted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BypassError {
+    Unknown,
+    Expired,
+    QuotaExceeded,
+    OutOfScope,
+}
+
+impl fmt::Display for BypassError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BypassError::Unknown => write!(f, "unknown token"),
+            BypassError::Expired => write!(f, "token expired"),
+            BypassError::QuotaExceeded => write!(f, "token usage quota exceeded"),
+            BypassError::OutOfScope => write!(f, "phone number is out of the token's scope"),
+        }
+    }
+}
+
+/// The verifier's bypass tokens
+#[derive(Default)]
+pub(crate) struct BypassTokens {
+    tokens: Vec<BypassToken>,
+}
+
+impl BypassTokens {
+    /// Loads the bypass tokens from the server config.
+    /// A non-empty legacy `verifier.bypass_token` value is added as an unrestricted token.
+    pub(crate) async fn from_config() -> Result<Self> {
+        let mut configs = ServerConfigService::get_typed::<Vec<BypassTokenConfig>>(
+            VERIFIER_BYPASS_TOKENS_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap_or_default();
+
+        if let Some(token) =
+            ServerConfigService::get(VERIFIER_BYPASS_TOKEN_CONFIG_KEY.into()).await?
+        {
+            if !token.is_empty() {
+                configs.push(BypassTokenConfig {
+                    name: LEGACY_TOKEN_NAME.into(),
+                    token,
+                    expires_at: None,
+                    max_uses: None,
+                    phone_number_prefix: None,
+                });
+            }
+        }
+
+        let mut tokens: Vec<BypassToken> = vec![];
+        for config in configs {
+            if config.token.is_empty() {
+                return Err(anyhow!("bypass token {} is empty", config.name));
+            }
+
+            if tokens.iter().any(|t| t.config.name == config.name) {
+                return Err(anyhow!("duplicate bypass token name: {}", config.name));
+            }
+
+            info!("Bypass token: {}", config.name);
+            tokens.push(BypassToken { config, uses: 0 });
+        }
+
+        Ok(BypassTokens { tokens })
+    }
+
+    /// Uses a token provided by a client to verify a normalized phone number.
+    /// Returns the token's name and writes an audit record when the token is accepted.
+    pub(crate) fn use_token(
+        &mut self,
+        token: &str,
+        phone_number: &str,
+        account_id: &str,
+    ) -> Result<String, BypassError> {
+        if token.is_empty() {
+            return Err(BypassError::Unknown);
+        }
+
+        // compare with all tokens in constant time so response times don't reveal tokens
+        let mut matched = None;
+        for (index, bypass_token) in self.tokens.iter().enumerate() {
+            if bool::from(bypass_token.config.token.as_bytes().ct_eq(token.as_bytes())) {
+                matched = Some(index);
+            }
+        }
+
+        let bypass_token = match matched {
+            Some(index) => &mut self.tokens[index],
+            None => {
+                warn!(target: AUDIT_LOG_TARGET, "unknown bypass token used. account: {}", account_id);
+                return Err(BypassError::Unknown);
+            }
+        };
+
+        let result = bypass_token.check(phone_number);
+        let name = bypass_token.config.name.clone();
+        if let Err(e) = result {
+            warn!(
+                target: AUDIT_LOG_TARGET,
+                "bypass token {} rejected: {}. account: {}",
+                name,
+                e,
+                account_id
+            );
+            return Err(e);
+        }
+
+        bypass_token.uses += 1;
+        info!(
+            target: AUDIT_LOG_TARGET,
+            "bypass token {} used. account: {}, uses: {}",
+            name,
+            account_id,
+            bypass_token.uses
+        );
+
+        Ok(name)
+    }
+}
+
+impl BypassToken {
+    /// Returns an error if the token can't be used to verify the number
+    fn check(&self, phone_number: &str) -> Result<(), BypassError> {
+        if let Some(expires_at) = self.config.expires_at {
+            if Utc::now().timestamp_millis() as u64 >= expires_at {
+                return Err(BypassError::Expired);
+            }
+        }
+
+        if let Some(max_uses) = self.config.max_uses {
+            if self.uses >= max_uses {
+                return Err(BypassError::QuotaExceeded);
+            }
+        }
+
+        if let Some(prefix) = &self.config.phone_number_prefix {
+            if !phone_number.starts_with(prefix.as_str()) {
+                return Err(BypassError::OutOfScope);
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::VerificationChannel;
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_DEFAULT_CHANNEL_CONFIG_KEY, VERIFIER_ENABLED_CHANNELS_CONFIG_KEY,
+};
+
+/// Returns the name of a channel as used in the server config
+pub(crate) fn channel_name(channel: VerificationChannel) -> &'static str {
+    match channel {
+        VerificationChannel::Unspecified => "unspecified",
+        VerificationChannel::Sms => "sms",
+        VerificationChannel::Voice => "voice",
+        VerificationChannel::Whatsapp => "whatsapp",
+    }
+}
+
+/// Returns the channel with the provided config name
+pub(crate) fn channel_from_name(name: &str) -> Result<VerificationChannel> {
+    match name.trim().to_lowercase().as_str() {
+        "sms" => Ok(VerificationChannel::Sms),
+        "voice" => Ok(VerificationChannel::Voice),
+        "whatsapp" => Ok(VerificationChannel::Whatsapp),
+        _ => Err(anyhow!("unknown verification channel: {}", name)),
+    }
+}
+
+/// Channels config of the verifier
+pub(crate) struct ChannelsConfig {
+    /// channel used when a request doesn't specify one
+    pub(crate) default_channel: VerificationChannel,
+    /// channels users may request codes on
+    pub(crate) enabled_channels: Vec<VerificationChannel>,
+}
+
+impl ChannelsConfig {
+    /// Loads the channels config from the server config.
+    /// Returns an error when a channel is unknown or when the default channel is not enabled.
+    pub(crate) async fn from_config() -> Result<Self> {
+        let default_channel = channel_from_name(
+            &ServerConfigService::get(VERIFIER_DEFAULT_CHANNEL_CONFIG_KEY.into())
+                .await?
+                .unwrap(),
+        )?;
+
+        let enabled_channels = ServerConfigService::get_typed::<Vec<String>>(
+            VERIFIER_ENABLED_CHANNELS_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap()
+        .iter()
+        .map(|name| channel_from_name(name))
+        .collect::<Result<Vec<VerificationChannel>>>()?;
+
+        if !enabled_channels.contains(&default_channel) {
+            return Err(anyhow!(
+                "default verification channel {} is not enabled",
+                channel_name(default_channel)
+            ));
+        }
+
+        Ok(ChannelsConfig {
+            default_channel,
+            enabled_channels,
+        })
+    }
+
+    /// Returns true if users may request codes on the channel
+    pub(crate) fn is_enabled(&self, channel: VerificationChannel) -> bool {
+        self.enabled_channels.contains(&channel)
+    }
+}
+
+impl Default for ChannelsConfig {
+    fn default() -> Self {
+        ChannelsConfig {
+            default_channel: VerificationChannel::Whatsapp,
+            enabled_channels: vec![VerificationChannel::Whatsapp],
+        }
+    }
+}
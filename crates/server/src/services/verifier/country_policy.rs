@@ -0,0 +1,57 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use anyhow::Result;
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_COUNTRY_ALLOWLIST_CONFIG_KEY,
+    VERIFIER_COUNTRY_BLOCKLIST_CONFIG_KEY,
+};
+
+/// Country calling codes the verifier sends codes to and verifies numbers of
+#[derive(Default)]
+pub(crate) struct CountryPolicy {
+    /// allowed country calling codes. All countries are allowed when empty
+    allowlist: Vec<u16>,
+    /// blocked country calling codes. Takes precedence over the allowlist
+    blocklist: Vec<u16>,
+}
+
+impl CountryPolicy {
+    /// Loads the country lists from the server config
+    pub(crate) async fn from_config() -> Result<Self> {
+        let allowlist = ServerConfigService::get_typed::<Vec<u16>>(
+            VERIFIER_COUNTRY_ALLOWLIST_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap_or_default();
+
+        let blocklist = ServerConfigService::get_typed::<Vec<u16>>(
+            VERIFIER_COUNTRY_BLOCKLIST_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap_or_default();
+
+        if !allowlist.is_empty() {
+            info!("Allowed country codes: {:?}", allowlist);
+        }
+
+        if !blocklist.is_empty() {
+            info!("Blocked country codes: {:?}", blocklist);
+        }
+
+        Ok(CountryPolicy {
+            allowlist,
+            blocklist,
+        })
+    }
+
+    /// Returns true if numbers with the country calling code may be verified
+    pub(crate) fn is_allowed(&self, country_code: u16) -> bool {
+        if self.blocklist.contains(&country_code) {
+            return false;
+        }
+
+        self.allowlist.is_empty() || self.allowlist.contains(&country_code)
+    }
+}
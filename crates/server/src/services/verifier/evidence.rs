@@ -0,0 +1,76 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::PhoneNumberHashScheme as ProtoPhoneNumberHashScheme;
+use parity_scale_codec::{Decode, Encode};
+use sp_core::crypto::AccountId32;
+use sp_core::ed25519::Public;
+
+/// Scheme an evidence phone number hash was computed with.
+/// Scale encoded as a single byte tag matching the PhoneNumberHashScheme proto enum values.
+#[derive(Encode, Decode, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PhoneNumberHashScheme {
+    /// unkeyed blake2 512 hash
+    #[codec(index = 1)]
+    Blake2_512,
+    /// hmac sha512 keyed with a verifier held pepper
+    #[codec(index = 2)]
+    HmacSha512,
+}
+
+impl From<PhoneNumberHashScheme> for ProtoPhoneNumberHashScheme {
+    fn from(scheme: PhoneNumberHashScheme) -> Self {
+        match scheme {
+            PhoneNumberHashScheme::Blake2_512 => ProtoPhoneNumberHashScheme::Blake2512,
+            PhoneNumberHashScheme::HmacSha512 => ProtoPhoneNumberHashScheme::HmacSha512,
+        }
+    }
+}
+
+/// Verification evidence signed by the verifier to attest that an account owns a mobile number.
+/// Scale encoded with the fields of the chain's VerificationEvidence, then its validity window so the chain
+/// and clients can reject stale evidence, then the tag of the scheme the phone number hash was computed with.
+/// The byte layout is documented next to UserVerificationData.evidence in verifier.proto - never reorder fields.
+#[derive(Encode, Decode, Debug, Clone, PartialEq, Eq)]
+pub(crate) struct VerificationEvidenceEnvelope {
+    pub(crate) verifier_public_key: Public,
+    pub(crate) account_id: AccountId32,
+    pub(crate) username: String,
+    /// hash of the E.164 phone number
+    pub(crate) phone_number_hash: [u8; 64],
+    /// time the evidence was issued at in milliseconds
+    pub(crate) issued_at: u64,
+    /// time the evidence expires at in milliseconds
+    pub(crate) expires_at: u64,
+    /// scheme the phone number hash was computed with
+    pub(crate) phone_number_hash_scheme: PhoneNumberHashScheme,
+}
+
+impl VerificationEvidenceEnvelope {
+    /// Creates evidence for an account's phone number hash which is valid for `ttl` milliseconds.
+    /// Returns an error if the expiry time overflows
+    pub(crate) fn new(
+        verifier_public_key: Public,
+        account_id: AccountId32,
+        username: String,
+        phone_number_hash: (PhoneNumberHashScheme, [u8; 64]),
+        issued_at: u64,
+        ttl: u64,
+    ) -> Result<Self> {
+        let expires_at = issued_at
+            .checked_add(ttl)
+            .ok_or_else(|| anyhow!("evidence expiry overflows. ttl: {}", ttl))?;
+
+        Ok(VerificationEvidenceEnvelope {
+            verifier_public_key,
+            account_id,
+            username,
+            phone_number_hash: phone_number_hash.1,
+            issued_at,
+            expires_at,
+            phone_number_hash_scheme: phone_number_hash.0,
+        })
+    }
+}
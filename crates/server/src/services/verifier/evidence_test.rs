@@ -0,0 +1,104 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::evidence::{PhoneNumberHashScheme, VerificationEvidenceEnvelope};
+use parity_scale_codec::{Decode, DecodeAll, Encode};
+use sp_core::crypto::AccountId32;
+use sp_core::ed25519::Public;
+
+const VERIFIER_PUBLIC_KEY: [u8; 32] = [1; 32];
+const ACCOUNT_ID: [u8; 32] = [2; 32];
+const USERNAME: &str = "alice";
+const PHONE_NUMBER_HASH: [u8; 64] = [3; 64];
+const ISSUED_AT: u64 = 1_671_000_000_000;
+const TTL: u64 = 600_000;
+
+fn evidence() -> VerificationEvidenceEnvelope {
+    VerificationEvidenceEnvelope::new(
+        Public(VERIFIER_PUBLIC_KEY),
+        AccountId32::new(ACCOUNT_ID),
+        USERNAME.into(),
+        (PhoneNumberHashScheme::HmacSha512, PHONE_NUMBER_HASH),
+        ISSUED_AT,
+        TTL,
+    )
+    .unwrap()
+}
+
+/// The chain decodes the evidence bytes verbatim so its layout must never change silently.
+/// Layout: verifier public key (32) || account id (32) || compact length prefixed utf8 username ||
+/// phone number hash (64) || issued_at (u64 le) || expires_at (u64 le) || hash scheme tag (1)
+#[test]
+fn evidence_encoding_matches_chain_layout() {
+    let mut expected = vec![];
+    expected.extend_from_slice(&VERIFIER_PUBLIC_KEY);
+    expected.extend_from_slice(&ACCOUNT_ID);
+    // compact encoded length of a short string is its length shifted left by 2
+    expected.push((USERNAME.len() as u8) << 2);
+    expected.extend_from_slice(USERNAME.as_bytes());
+    expected.extend_from_slice(&PHONE_NUMBER_HASH);
+    expected.extend_from_slice(&ISSUED_AT.to_le_bytes());
+    expected.extend_from_slice(&(ISSUED_AT + TTL).to_le_bytes());
+    expected.push(2);
+
+    assert_eq!(evidence().encode(), expected);
+}
+
+/// The evidence starts with the chain's VerificationEvidence encoding so the chain's decoder reads its fields
+#[test]
+fn evidence_encoding_extends_chain_evidence() {
+    let chain_evidence = sp_rpc::verifier::VerificationEvidence {
+        verifier_public_key: Public(VERIFIER_PUBLIC_KEY),
+        account_id: AccountId32::new(ACCOUNT_ID),
+        username: USERNAME.into(),
+        phone_number_hash: PHONE_NUMBER_HASH,
+    };
+    let chain_bytes = chain_evidence.encode();
+    let bytes = evidence().encode();
+    assert_eq!(&bytes[..chain_bytes.len()], chain_bytes.as_slice());
+
+    let decoded = sp_rpc::verifier::VerificationEvidence::decode(&mut bytes.as_slice()).unwrap();
+    assert_eq!(decoded.encode(), chain_bytes);
+}
+
+#[test]
+fn hash_scheme_tags_match_proto_enum() {
+    assert_eq!(PhoneNumberHashScheme::Blake2_512.encode(), vec![1]);
+    assert_eq!(PhoneNumberHashScheme::HmacSha512.encode(), vec![2]);
+}
+
+#[test]
+fn evidence_expiry_overflow() {
+    assert!(VerificationEvidenceEnvelope::new(
+        Public(VERIFIER_PUBLIC_KEY),
+        AccountId32::new(ACCOUNT_ID),
+        USERNAME.into(),
+        (PhoneNumberHashScheme::HmacSha512, PHONE_NUMBER_HASH),
+        ISSUED_AT,
+        u64::MAX,
+    )
+    .is_err());
+}
+
+#[test]
+fn decode_evidence() {
+    let evidence = evidence();
+    let decoded =
+        VerificationEvidenceEnvelope::decode_all(&mut evidence.encode().as_slice()).unwrap();
+    assert_eq!(decoded, evidence);
+}
+
+#[test]
+fn decode_rejects_untagged_evidence() {
+    let mut bytes = evidence().encode();
+    bytes.pop();
+    assert!(VerificationEvidenceEnvelope::decode_all(&mut bytes.as_slice()).is_err());
+}
+
+#[test]
+fn decode_rejects_trailing_bytes() {
+    let mut bytes = evidence().encode();
+    bytes.push(0);
+    assert!(VerificationEvidenceEnvelope::decode_all(&mut bytes.as_slice()).is_err());
+}
@@ -0,0 +1,48 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::pre_key_service::{GetSignedPreKeyBundle, PreKeyService};
+use crate::services::verifier::verifier_service::VerifierService;
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::PreKeyBundle;
+use chrono::Utc;
+use std::net::IpAddr;
+use xactor::*;
+
+#[message(result = "Result<PreKeyBundle>")]
+pub(crate) struct GetPreKeyBundle {
+    /// address of the calling client - used for rate limiting
+    pub(crate) client_ip: Option<IpAddr>,
+}
+
+/// Request for the pre-key bundle clients encrypt phone numbers to
+#[async_trait::async_trait]
+impl Handler<GetPreKeyBundle> for VerifierService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetPreKeyBundle,
+    ) -> Result<PreKeyBundle> {
+        self.rate_limits.check_client_ip(msg.client_ip)?;
+
+        self.pre_key_bundle().await
+    }
+}
+
+impl VerifierService {
+    /// Returns the published pre-key bundle signed by the current verifier signing key
+    pub(crate) async fn pre_key_bundle(&self) -> Result<PreKeyBundle> {
+        let signer = self
+            .key_ring
+            .signing_key(Utc::now().timestamp_millis() as u64)
+            .ok_or_else(|| anyhow!("no active verifier signing key"))?
+            .signer
+            .clone();
+
+        PreKeyService::from_registry()
+            .await?
+            .call(GetSignedPreKeyBundle { signer })
+            .await?
+    }
+}
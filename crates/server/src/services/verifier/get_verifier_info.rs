@@ -0,0 +1,76 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::verifier_service::VerifierService;
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::{EvidenceFormat, PhoneNumberHashScheme, VerifierInfo};
+use chrono::Utc;
+use sp_core::crypto::Ss58Codec;
+use std::net::IpAddr;
+use xactor::*;
+
+/// Version of the verifier api. Bumped on breaking api or evidence changes
+pub(crate) const VERIFIER_API_VERSION: &str = "2.0.0";
+
+#[message(result = "Result<VerifierInfo>")]
+pub(crate) struct GetVerifierInfo {
+    /// address of the calling client - used for rate limiting
+    pub(crate) client_ip: Option<IpAddr>,
+}
+
+/// Request for the verifier's identity, keys and capabilities
+#[async_trait::async_trait]
+impl Handler<GetVerifierInfo> for VerifierService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetVerifierInfo,
+    ) -> Result<VerifierInfo> {
+        self.rate_limits.check_client_ip(msg.client_ip)?;
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let signing_key = self
+            .key_ring
+            .signing_key(now)
+            .ok_or_else(|| anyhow!("no active verifier signing key"))?;
+        let public_key = signing_key.public_key;
+
+        // clients can still verify without encrypting their requests
+        let pre_key_bundle = match self.pre_key_bundle().await {
+            Ok(bundle) => Some(bundle),
+            Err(e) => {
+                warn!("failed to get pre-key bundle: {}", e);
+                None
+            }
+        };
+
+        let public_key_ss58 = match self.ss58_prefix {
+            Some(prefix) => public_key.to_ss58check_with_version(prefix.into()),
+            None => public_key.to_ss58check(),
+        };
+
+        Ok(VerifierInfo {
+            name: self.name.clone(),
+            public_key: public_key.0.to_vec(),
+            public_key_ss58,
+            past_public_keys: self
+                .key_ring
+                .retired_public_keys(now)
+                .iter()
+                .map(|key| key.0.to_vec())
+                .collect(),
+            channels: self
+                .channels
+                .enabled_channels
+                .iter()
+                .map(|channel| *channel as i32)
+                .collect(),
+            evidence_formats: vec![EvidenceFormat::ScaleEd25519V2 as i32],
+            api_version: VERIFIER_API_VERSION.into(),
+            phone_number_hash_scheme: PhoneNumberHashScheme::from(self.phone_number_hasher.scheme())
+                as i32,
+            pre_key_bundle,
+        })
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! The verifier's signing keys. Each key signs evidence from its activation time until it is retired or
+//! a newer key becomes active, so keys can be rotated with overlapping validity by configuring the next
+//! key ahead of time. Keys which signed evidence in the past remain valid for evidence verification.
+//! A key is derived from a mnemonic, unlocked from a keystore file or held by a remote signer.
+
+use crate::keystore::DEFAULT_KEYSTORE_PASSWORD_ENV;
+use crate::services::verifier::signers::{create_signer, Signer, SignerConfig};
+use anyhow::{anyhow, Result};
+use base::hex_utils::hex_string;
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_KEYSTORE_CONFIG_KEY, VERIFIER_KEYSTORE_PASSWORD_ENV_CONFIG_KEY,
+    VERIFIER_KEYS_CONFIG_KEY, VERIFIER_KEY_MNEMONIC_CONFIG_KEY, VERIFIER_KEY_PASSWORD_CONFIG_KEY,
+    VERIFIER_PAST_PUBLIC_KEYS_CONFIG_KEY,
+};
+use serde::Deserialize;
+use sp_core::crypto::{ByteArray, Ss58Codec};
+use sp_core::ed25519::Public;
+use std::sync::Arc;
+
+/// Config values the signing keys are loaded from - the only values reloaded on SIGHUP
+pub(crate) const KEY_RING_CONFIG_KEYS: [&str; 6] = [
+    VERIFIER_KEYS_CONFIG_KEY,
+    VERIFIER_PAST_PUBLIC_KEYS_CONFIG_KEY,
+    VERIFIER_KEYSTORE_CONFIG_KEY,
+    VERIFIER_KEYSTORE_PASSWORD_ENV_CONFIG_KEY,
+    VERIFIER_KEY_MNEMONIC_CONFIG_KEY,
+    VERIFIER_KEY_PASSWORD_CONFIG_KEY,
+];
+
+/// Name of the key configured with the single key keystore or key_mnemonic config values
+const SINGLE_KEY_NAME: &str = "default";
+
+/// A signing key as defined in the server config. One of mnemonic, keystore or remote_signer must be set
+#[derive(Deserialize, Clone, Default)]
+struct SigningKeyConfig {
+    name: String,
+    mnemonic: Option<String>,
+    /// mnemonic password
+    password: Option<String>,
+    /// path of a keystore file
+    keystore: Option<String>,
+    /// env var holding the keystore file's password
+    password_env: Option<String>,
+    /// path of a remote signer's unix socket
+    remote_signer: Option<String>,
+    /// name of the key in the remote signer - the signer's default key when not set
+    remote_key_name: Option<String>,
+    /// time the key starts signing evidence at in milliseconds
+    active_from: Option<u64>,
+    /// time the key stops signing evidence at in milliseconds
+    retire_at: Option<u64>,
+}
+
+impl SigningKeyConfig {
+    /// Returns the config of the key's signer
+    fn signer_config(&self) -> Result<SignerConfig> {
+        match (&self.mnemonic, &self.keystore, &self.remote_signer) {
+            (Some(mnemonic), None, None) => Ok(SignerConfig::Mnemonic {
+                mnemonic: mnemonic.clone(),
+                password: self.password.clone(),
+            }),
+            (None, Some(path), None) => {
+                let password_env = self
+                    .password_env
+                    .as_deref()
+                    .unwrap_or(DEFAULT_KEYSTORE_PASSWORD_ENV);
+                let password = std::env::var(password_env).map_err(|_| {
+                    anyhow!(
+                        "keystore password of verifier key {} not set in {}",
+                        self.name,
+                        password_env
+                    )
+                })?;
+                Ok(SignerConfig::Keystore {
+                    path: path.clone(),
+                    password,
+                })
+            }
+            (None, None, Some(socket_path)) => Ok(SignerConfig::Remote {
+                socket_path: socket_path.clone(),
+                key_name: self.remote_key_name.clone().unwrap_or_default(),
+            }),
+            _ => Err(anyhow!(
+                "verifier key {} must set one of mnemonic, keystore or remote_signer",
+                self.name
+            )),
+        }
+    }
+}
+
+/// A verifier signing key and its validity window
+pub(crate) struct SigningKey {
+    pub(crate) name: String,
+    pub(crate) signer: Arc<dyn Signer>,
+    pub(crate) public_key: Public,
+    /// time the key starts signing evidence at in milliseconds
+    pub(crate) active_from: u64,
+    /// time the key stops signing evidence at in milliseconds
+    pub(crate) retire_at: Option<u64>,
+}
+
+impl SigningKey {
+    /// Returns true if the key may sign evidence at the provided time
+    fn is_active(&self, now: u64) -> bool {
+        self.active_from <= now && self.retire_at.is_none_or(|retire_at| now < retire_at)
+    }
+}
+
+/// The verifier's signing keys and the public keys of keys it used in the past
+#[derive(Default)]
+pub(crate) struct KeyRing {
+    keys: Vec<SigningKey>,
+    /// public keys of keys which are no longer configured
+    past_public_keys: Vec<Public>,
+}
+
+impl KeyRing {
+    /// Loads the signing keys from the server config.
+    /// The verifier's single key is used when no keys are configured.
+    pub(crate) async fn from_config() -> Result<Self> {
+        let mut configs = ServerConfigService::get_typed::<Vec<SigningKeyConfig>>(
+            VERIFIER_KEYS_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap_or_default();
+
+        if configs.is_empty() {
+            configs.push(KeyRing::single_key_config().await?);
+        }
+
+        let mut keys: Vec<SigningKey> = vec![];
+        for config in configs {
+            if keys.iter().any(|k| k.name == config.name) {
+                return Err(anyhow!("duplicate verifier key name: {}", config.name));
+            }
+
+            let active_from = config.active_from.unwrap_or_default();
+            if let Some(retire_at) = config.retire_at {
+                if retire_at <= active_from {
+                    return Err(anyhow!(
+                        "verifier key {} is retired before it is active",
+                        config.name
+                    ));
+                }
+            }
+
+            let signer = create_signer(config.signer_config()?)
+                .await
+                .map_err(|e| anyhow!("failed to load verifier key {}: {:?}", config.name, e))?;
+            let public_key = signer.public_key();
+
+            info!(
+                "Verifier key {} public key raw: {}, ss58: {}",
+                config.name,
+                hex_string(&public_key.to_raw_vec()),
+                public_key
+            );
+
+            keys.push(SigningKey {
+                name: config.name,
+                signer,
+                public_key,
+                active_from,
+                retire_at: config.retire_at,
+            });
+        }
+
+        let past_public_keys = ServerConfigService::get_typed::<Vec<String>>(
+            VERIFIER_PAST_PUBLIC_KEYS_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap_or_default()
+        .iter()
+        .map(|key| {
+            Public::from_ss58check(key)
+                .map_err(|e| anyhow!("invalid past verifier public key {}: {:?}", key, e))
+        })
+        .collect::<Result<Vec<Public>>>()?;
+
+        Ok(KeyRing {
+            keys,
+            past_public_keys,
+        })
+    }
+
+    /// Returns the config of the verifier's single key, used when no keys are configured.
+    /// The key is unlocked from `verifier.keystore` when set, or derived from `verifier.key_mnemonic`.
+    async fn single_key_config() -> Result<SigningKeyConfig> {
+        if let Some(keystore) =
+            ServerConfigService::get(VERIFIER_KEYSTORE_CONFIG_KEY.into()).await?
+        {
+            let password_env =
+                ServerConfigService::get(VERIFIER_KEYSTORE_PASSWORD_ENV_CONFIG_KEY.into()).await?;
+
+            return Ok(SigningKeyConfig {
+                name: SINGLE_KEY_NAME.into(),
+                keystore: Some(keystore),
+                password_env,
+                ..Default::default()
+            });
+        }
+
+        let mnemonic = ServerConfigService::get(VERIFIER_KEY_MNEMONIC_CONFIG_KEY.into())
+            .await?
+            .ok_or_else(|| anyhow!("no verifier signing keys configured"))?;
+
+        warn!(
+            "verifier key is derived from a mnemonic in the config file - prefer a keystore file"
+        );
+
+        let password = ServerConfigService::get(VERIFIER_KEY_PASSWORD_CONFIG_KEY.into()).await?;
+
+        Ok(SigningKeyConfig {
+            name: SINGLE_KEY_NAME.into(),
+            mnemonic: Some(mnemonic),
+            password,
+            ..Default::default()
+        })
+    }
+
+    /// Returns the key which signs evidence at the provided time - the most recently activated active key
+    pub(crate) fn signing_key(&self, now: u64) -> Option<&SigningKey> {
+        self.keys
+            .iter()
+            .filter(|key| key.is_active(now))
+            .max_by_key(|key| key.active_from)
+    }
+
+    /// Returns true if evidence signed with the public key may be verified at the provided time.
+    /// Keys which aren't active yet are not accepted as they can't have signed evidence.
+    pub(crate) fn is_verification_key(&self, public_key: &Public, now: u64) -> bool {
+        self.past_public_keys.contains(public_key)
+            || self
+                .keys
+                .iter()
+                .any(|key| key.active_from <= now && key.public_key == *public_key)
+    }
+
+    /// Returns the public keys which signed evidence in the past and no longer sign evidence
+    pub(crate) fn retired_public_keys(&self, now: u64) -> Vec<Public> {
+        let signing_key = self.signing_key(now).map(|key| key.public_key);
+        self.keys
+            .iter()
+            .filter(|key| key.active_from <= now)
+            .map(|key| key.public_key)
+            .filter(|public_key| Some(*public_key) != signing_key)
+            .chain(self.past_public_keys.iter().copied())
+            .collect()
+    }
+}
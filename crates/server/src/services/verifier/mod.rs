@@ -5,7 +5,27 @@
 /// The Verifier module provides the KarmaCoin mobile phone verification api to users.
 /// Users use the verifier to verify their mobile phone number on-chain.
 /// For further details read the KarmaCoin docs and onboarding flows.
+pub(crate) mod bypass_tokens;
+pub(crate) mod channels;
+pub(crate) mod country_policy;
+pub(crate) mod evidence;
+#[cfg(test)]
+mod evidence_test;
+pub(crate) mod get_pre_key_bundle;
+pub(crate) mod get_verifier_info;
+pub(crate) mod key_ring;
+pub(crate) mod phone_number;
+pub(crate) mod phone_number_hasher;
+pub(crate) mod pre_key_service;
+pub(crate) mod providers;
+pub(crate) mod rate_limiter;
+pub(crate) mod replay_guard;
+pub(crate) mod request_validation;
 pub(crate) mod verifier_service;
+pub(crate) mod verify_evidence;
 pub(crate) mod verify_number;
 
 pub(crate) mod send_verification_code;
+pub(crate) mod sessions;
+pub(crate) mod signers;
+pub(crate) mod username_policy;
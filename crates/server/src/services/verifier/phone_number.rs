@@ -0,0 +1,31 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use anyhow::{anyhow, Result};
+use phonenumber::Mode;
+
+/// A validated international phone number
+pub(crate) struct NormalizedPhoneNumber {
+    /// number in E.164 format
+    pub(crate) number: String,
+    /// country calling code of the number
+    pub(crate) country_code: u16,
+}
+
+/// Parses and validates an international mobile number and returns it in E.164 format.
+/// Numbers are normalized before they are sent to providers, checked and hashed so
+/// different formatting of the same number always results in the same phone number hash.
+pub(crate) fn normalize_phone_number(number: &str) -> Result<NormalizedPhoneNumber> {
+    let parsed = phonenumber::parse(None, number)
+        .map_err(|e| anyhow!("failed to parse phone number: {:?}", e))?;
+
+    if !parsed.is_valid() {
+        return Err(anyhow!("invalid phone number"));
+    }
+
+    Ok(NormalizedPhoneNumber {
+        number: parsed.format().mode(Mode::E164).to_string(),
+        country_code: parsed.code().value(),
+    })
+}
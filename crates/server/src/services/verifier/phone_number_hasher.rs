@@ -0,0 +1,90 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Phone number hashing for evidence. An unkeyed hash of a phone number can be reversed by hashing
+//! the whole phone number space, so numbers may be hashed with hmac sha512 keyed with a secret pepper
+//! held by the verifier. The scheme is tagged in the evidence so the chain can tell hashes apart.
+//! Changing the scheme or the pepper changes the hash of every number, so it must be coordinated
+//! with the chain's phone number uniqueness checks.
+
+use crate::services::verifier::evidence::PhoneNumberHashScheme;
+use anyhow::{anyhow, Result};
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_PHONE_NUMBER_HASH_PEPPER_CONFIG_KEY,
+    VERIFIER_PHONE_NUMBER_HASH_SCHEME_CONFIG_KEY,
+};
+use crypto::hmacer::Hmacer;
+
+/// Minimum pepper length in bytes
+const MIN_PEPPER_LEN: usize = 32;
+
+/// Hashes normalized phone numbers with the configured scheme
+pub(crate) struct PhoneNumberHasher {
+    scheme: PhoneNumberHashScheme,
+    /// hmac key - empty for unkeyed schemes
+    pepper: Vec<u8>,
+}
+
+impl Default for PhoneNumberHasher {
+    fn default() -> Self {
+        PhoneNumberHasher {
+            scheme: PhoneNumberHashScheme::Blake2_512,
+            pepper: vec![],
+        }
+    }
+}
+
+impl PhoneNumberHasher {
+    /// Loads the hash scheme and pepper from the server config
+    pub(crate) async fn from_config() -> Result<Self> {
+        let scheme = ServerConfigService::get(VERIFIER_PHONE_NUMBER_HASH_SCHEME_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+
+        match scheme.as_str() {
+            "blake2_512" => {
+                warn!("phone numbers are hashed without a pepper - prefer the hmac_sha512 scheme");
+                Ok(PhoneNumberHasher::default())
+            }
+            "hmac_sha512" => {
+                let pepper =
+                    ServerConfigService::get(VERIFIER_PHONE_NUMBER_HASH_PEPPER_CONFIG_KEY.into())
+                        .await?
+                        .ok_or_else(|| anyhow!("phone number hash pepper not configured"))?;
+                let pepper = hex::decode(pepper)
+                    .map_err(|e| anyhow!("invalid phone number hash pepper: {}", e))?;
+                if pepper.len() < MIN_PEPPER_LEN {
+                    return Err(anyhow!(
+                        "phone number hash pepper must be at least {} bytes",
+                        MIN_PEPPER_LEN
+                    ));
+                }
+                Ok(PhoneNumberHasher {
+                    scheme: PhoneNumberHashScheme::HmacSha512,
+                    pepper,
+                })
+            }
+            _ => Err(anyhow!("unknown phone number hash scheme: {}", scheme)),
+        }
+    }
+
+    /// Returns the scheme numbers are hashed with
+    pub(crate) fn scheme(&self) -> PhoneNumberHashScheme {
+        self.scheme
+    }
+
+    /// Returns the hash scheme and hash of an E.164 phone number
+    pub(crate) fn hash(&self, phone_number: &str) -> Result<(PhoneNumberHashScheme, [u8; 64])> {
+        let hash = match self.scheme {
+            PhoneNumberHashScheme::Blake2_512 => {
+                sp_core::hashing::blake2_512(phone_number.as_bytes())
+            }
+            PhoneNumberHashScheme::HmacSha512 => {
+                let tag = Hmacer::hmac_sha512(&self.pepper, phone_number.as_bytes())?;
+                tag.unprotected_as_bytes().try_into()?
+            }
+        };
+        Ok((self.scheme, hash))
+    }
+}
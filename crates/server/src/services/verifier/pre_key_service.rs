@@ -0,0 +1,319 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Manages the verifier's X2DH pre-keys. Clients encrypt phone numbers to the published pre-key bundle
+//! so numbers are protected even when TLS is terminated by a proxy in front of the verifier.
+//! A new pre-key is created every rotation period and a replaced pre-key still decrypts payloads for a
+//! grace period, so clients which fetched its bundle before the rotation can complete their requests.
+//! Bundles are signed by the verifier signing key so clients can authenticate them with the pinned verifier key.
+//! The X2DH identity key and unexpired pre-keys are persisted to the configured pre-key store and loaded on
+//! startup. Without a store they are created again when the verifier restarts.
+
+use crate::pre_key_store::{
+    PreKeyStore, StoredPreKey, StoredPreKeys, DEFAULT_PRE_KEY_STORE_PASSWORD_ENV,
+};
+use crate::services::verifier::signers::Signer;
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::{EncryptedPayload, PreKeyBundle};
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_PRE_KEY_GRACE_SECS_CONFIG_KEY,
+    VERIFIER_PRE_KEY_ROTATION_SECS_CONFIG_KEY, VERIFIER_PRE_KEY_STORE_CONFIG_KEY,
+    VERIFIER_PRE_KEY_STORE_PASSWORD_ENV_CONFIG_KEY,
+};
+use chrono::Utc;
+use crypto::x2dh::{decrypt_bob, execute_bob, ProtocolInputBob};
+use ed25519_dalek::{Keypair, PublicKey, SecretKey};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use std::path::Path;
+use std::sync::Arc;
+use x25519_dalek::StaticSecret;
+use xactor::*;
+
+/// Prefix of signed pre-key bundle data - separates bundle signatures from evidence signatures
+const PRE_KEY_BUNDLE_SIGNING_CONTEXT: &[u8] = b"karmacoin-pre-key-bundle";
+
+/// An x25519 pre-key and its bundle
+struct PreKey {
+    secret: StaticSecret,
+    /// bundle signed by the signing key it was last published with - unsigned until first published
+    bundle: PreKeyBundle,
+}
+
+/// PreKeyService creates, rotates and expires the pre-keys clients encrypt requests to
+#[derive(Default)]
+pub(crate) struct PreKeyService {
+    /// secret of the X2DH ed25519 identity key - loaded from the store or created on startup
+    identity_secret: [u8; 32],
+    /// public X2DH identity key
+    identity_key: [u8; 32],
+    /// unexpired pre-keys by creation time - the last one is published
+    pre_keys: Vec<PreKey>,
+    /// time a pre-key is published for in milliseconds
+    rotation_period: u64,
+    /// time a replaced pre-key still decrypts payloads in milliseconds
+    grace_period: u64,
+    /// store the keys are persisted to when configured
+    store: Option<PreKeyStore>,
+}
+
+#[async_trait::async_trait]
+impl Actor for PreKeyService {
+    async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
+        let rotation_secs =
+            ServerConfigService::get_u64(VERIFIER_PRE_KEY_ROTATION_SECS_CONFIG_KEY.into())
+                .await?
+                .unwrap();
+        self.rotation_period = rotation_secs
+            .checked_mul(1000)
+            .ok_or_else(|| anyhow!("pre-key rotation period is too long: {}", rotation_secs))?;
+
+        let grace_secs =
+            ServerConfigService::get_u64(VERIFIER_PRE_KEY_GRACE_SECS_CONFIG_KEY.into())
+                .await?
+                .unwrap();
+        self.grace_period = grace_secs
+            .checked_mul(1000)
+            .ok_or_else(|| anyhow!("pre-key grace period is too long: {}", grace_secs))?;
+
+        if self.rotation_period == 0 {
+            return Err(anyhow!("pre-key rotation period must be positive"));
+        }
+
+        let stored_keys = match ServerConfigService::get(VERIFIER_PRE_KEY_STORE_CONFIG_KEY.into())
+            .await?
+        {
+            Some(path) => {
+                let password_env =
+                    ServerConfigService::get(VERIFIER_PRE_KEY_STORE_PASSWORD_ENV_CONFIG_KEY.into())
+                        .await?
+                        .unwrap_or_else(|| DEFAULT_PRE_KEY_STORE_PASSWORD_ENV.into());
+                let password = std::env::var(&password_env)
+                    .map_err(|_| anyhow!("pre-key store password not set in {}", password_env))?;
+
+                let (store, stored_keys) = PreKeyStore::open(Path::new(&path), &password)?;
+                self.store = Some(store);
+                stored_keys
+            }
+            None => {
+                warn!("no pre-key store configured. pre-keys are created again on restart");
+                None
+            }
+        };
+
+        match stored_keys {
+            Some(stored_keys) => {
+                info!(
+                    "loaded {} pre-keys from the pre-key store",
+                    stored_keys.pre_keys.len()
+                );
+                self.load(stored_keys)?;
+            }
+            None => {
+                OsRng.fill_bytes(&mut self.identity_secret);
+                self.identity_key =
+                    PublicKey::from(&SecretKey::from_bytes(&self.identity_secret)?).to_bytes();
+            }
+        }
+
+        self.rotate(Utc::now().timestamp_millis() as u64);
+        self.save()?;
+
+        info!("pre-key service started");
+        Ok(())
+    }
+}
+
+impl Service for PreKeyService {}
+
+impl PreKeyService {
+    /// Drops expired pre-keys and creates a pre-key when the published one is due for rotation.
+    /// Returns true if the pre-keys changed
+    fn rotate(&mut self, now: u64) -> bool {
+        let count = self.pre_keys.len();
+        self.pre_keys
+            .retain(|pre_key| now < pre_key.bundle.expires_at);
+
+        let rotation_due = self
+            .pre_keys
+            .last()
+            .is_none_or(|pre_key| now >= pre_key.bundle.created_at + self.rotation_period);
+        if !rotation_due {
+            return self.pre_keys.len() != count;
+        }
+
+        let mut secret = [0u8; 32];
+        OsRng.fill_bytes(&mut secret);
+        let pre_key = self.pre_key(
+            StaticSecret::from(secret),
+            OsRng.next_u64(),
+            now,
+            now + self.rotation_period + self.grace_period,
+        );
+
+        info!(
+            "created pre-key bundle {}. expires at: {}",
+            pre_key.bundle.bundle_id, pre_key.bundle.expires_at
+        );
+        self.pre_keys.push(pre_key);
+        true
+    }
+
+    /// Rotates the pre-keys and persists them when they changed
+    fn update(&mut self, now: u64) {
+        if self.rotate(now) {
+            if let Err(e) = self.save() {
+                warn!("failed to save pre-keys: {}", e);
+            }
+        }
+    }
+
+    /// Returns a pre-key with an unsigned bundle of the identity key
+    fn pre_key(
+        &self,
+        secret: StaticSecret,
+        bundle_id: u64,
+        created_at: u64,
+        expires_at: u64,
+    ) -> PreKey {
+        let bundle = PreKeyBundle {
+            bundle_id,
+            identity_key: self.identity_key.to_vec(),
+            pre_key: x25519_dalek::PublicKey::from(&secret).as_bytes().to_vec(),
+            created_at,
+            expires_at,
+            ..Default::default()
+        };
+        PreKey { secret, bundle }
+    }
+
+    /// Sets the identity key and pre-keys loaded from the store.
+    /// Bundles are signed again when they are published.
+    fn load(&mut self, stored_keys: StoredPreKeys) -> Result<()> {
+        self.identity_secret = stored_keys.identity_secret;
+        self.identity_key =
+            PublicKey::from(&SecretKey::from_bytes(&self.identity_secret)?).to_bytes();
+
+        self.pre_keys = stored_keys
+            .pre_keys
+            .into_iter()
+            .map(|pre_key| {
+                self.pre_key(
+                    StaticSecret::from(pre_key.secret),
+                    pre_key.bundle_id,
+                    pre_key.created_at,
+                    pre_key.expires_at,
+                )
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Persists the identity key and pre-keys to the store when one is configured
+    fn save(&self) -> Result<()> {
+        let store = match &self.store {
+            Some(store) => store,
+            None => return Ok(()),
+        };
+
+        store.save(&StoredPreKeys {
+            identity_secret: self.identity_secret,
+            pre_keys: self
+                .pre_keys
+                .iter()
+                .map(|pre_key| StoredPreKey {
+                    secret: pre_key.secret.to_bytes(),
+                    bundle_id: pre_key.bundle.bundle_id,
+                    created_at: pre_key.bundle.created_at,
+                    expires_at: pre_key.bundle.expires_at,
+                })
+                .collect(),
+        })
+    }
+}
+
+#[message(result = "Result<PreKeyBundle>")]
+pub(crate) struct GetSignedPreKeyBundle {
+    /// current verifier signing key
+    pub(crate) signer: Arc<dyn Signer>,
+}
+
+/// Returns the published pre-key bundle signed by the current verifier signing key
+#[async_trait::async_trait]
+impl Handler<GetSignedPreKeyBundle> for PreKeyService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: GetSignedPreKeyBundle,
+    ) -> Result<PreKeyBundle> {
+        self.update(Utc::now().timestamp_millis() as u64);
+
+        let pre_key = self
+            .pre_keys
+            .last_mut()
+            .ok_or_else(|| anyhow!("no pre-key"))?;
+
+        // sign again after the verifier signing keys were rotated
+        let public_key = msg.signer.public_key();
+        if pre_key.bundle.verifier_public_key != public_key.0 {
+            let signature = msg
+                .signer
+                .sign(&bundle_signed_data(&pre_key.bundle))
+                .await?;
+            pre_key.bundle.verifier_public_key = public_key.0.to_vec();
+            pre_key.bundle.signature = signature.0.to_vec();
+        }
+
+        Ok(pre_key.bundle.clone())
+    }
+}
+
+#[message(result = "Result<Vec<u8>>")]
+pub(crate) struct Decrypt(pub(crate) EncryptedPayload);
+
+/// Decrypts a payload encrypted to an unexpired pre-key bundle
+#[async_trait::async_trait]
+impl Handler<Decrypt> for PreKeyService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, msg: Decrypt) -> Result<Vec<u8>> {
+        let payload = msg.0;
+        self.update(Utc::now().timestamp_millis() as u64);
+
+        let pre_key = self
+            .pre_keys
+            .iter()
+            .find(|pre_key| pre_key.bundle.bundle_id == payload.bundle_id)
+            .ok_or_else(|| anyhow!("unknown or expired pre-key bundle: {}", payload.bundle_id))?;
+
+        let eka: [u8; 32] = payload
+            .ephemeral_key
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow!("invalid ephemeral key"))?;
+
+        let secret = SecretKey::from_bytes(&self.identity_secret)?;
+        let public = PublicKey::from(&secret);
+        let output = execute_bob(&ProtocolInputBob {
+            eka: x25519_dalek::PublicKey::from(eka),
+            ikb_pair: Keypair { secret, public },
+            pkb_private: pre_key.secret.clone(),
+            b_bundle_id: payload.bundle_id,
+        });
+
+        decrypt_bob(&output, &payload.ciphertext)
+    }
+}
+
+/// Returns the data a pre-key bundle is signed over
+fn bundle_signed_data(bundle: &PreKeyBundle) -> Vec<u8> {
+    let bundle_id = bundle.bundle_id.to_le_bytes();
+    let expires_at = bundle.expires_at.to_le_bytes();
+    let parts: [&[u8]; 5] = [
+        PRE_KEY_BUNDLE_SIGNING_CONTEXT,
+        &bundle_id,
+        &bundle.identity_key,
+        &bundle.pre_key,
+        &expires_at,
+    ];
+    parts.concat()
+}
@@ -0,0 +1,177 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::channels::channel_name;
+use crate::services::verifier::providers::{
+    OTPVerifyRequest, OTPVerifyResponse, ProviderError, ProviderResult, VerificationProvider,
+    APPROVED_STATUS,
+};
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::VerificationChannel;
+use base::server_config_service::{
+    ServerConfigService, MOCK_PROVIDER_CODE_CONFIG_KEY, MOCK_PROVIDER_ENABLED_CONFIG_KEY,
+};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+pub(crate) const MOCK_PROVIDER_NAME: &str = "mock";
+
+const PENDING_STATUS: &str = "pending";
+
+/// Http status code returned by Twilio when there's no pending verification for a number
+const NOT_FOUND_STATUS_CODE: u16 = 404;
+
+/// A pending mock verification session
+struct MockSession {
+    sid: String,
+    code: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    /// pending sessions by mobile number
+    sessions: HashMap<String, MockSession>,
+    /// used to generate deterministic session ids
+    sessions_count: u64,
+}
+
+/// An in-process provider for local development and tests.
+/// It doesn't send any messages - every session is issued the configured code which is logged at debug level.
+/// Sessions are kept in memory and follow the Twilio Verify sid and status semantics.
+pub(crate) struct MockProvider {
+    code: String,
+    state: Mutex<MockState>,
+}
+
+impl MockProvider {
+    /// Creates a provider from the mock section of the server config.
+    /// Returns an error unless the mock provider is explicitly enabled.
+    pub(crate) async fn from_config() -> Result<Self> {
+        let enabled = ServerConfigService::get_bool(MOCK_PROVIDER_ENABLED_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+        if !enabled {
+            return Err(anyhow!(
+                "mock verification provider is configured but not enabled - set {}",
+                MOCK_PROVIDER_ENABLED_CONFIG_KEY
+            ));
+        }
+
+        let code = ServerConfigService::get(MOCK_PROVIDER_CODE_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+
+        warn!("mock verification provider is enabled - codes are not sent to users. Never enable it in production");
+
+        Ok(MockProvider::new(code))
+    }
+
+    /// Creates a provider which issues the provided code
+    pub(crate) fn new(code: String) -> Self {
+        MockProvider {
+            code,
+            state: Mutex::new(MockState::default()),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl VerificationProvider for MockProvider {
+    fn name(&self) -> &'static str {
+        MOCK_PROVIDER_NAME
+    }
+
+    async fn send_code(
+        &self,
+        mobile_number: &str,
+        channel: VerificationChannel,
+    ) -> ProviderResult<OTPVerifyRequest> {
+        if channel == VerificationChannel::Unspecified {
+            return Err(ProviderError::UnsupportedChannel(channel));
+        }
+
+        let mut state = self.state.lock().unwrap();
+
+        // a new code request for a number with a pending session re-uses the session
+        if let Some(session) = state.sessions.get(mobile_number) {
+            debug!(
+                "mock code via {}: {}. Session id: {}",
+                channel_name(channel),
+                session.code,
+                session.sid
+            );
+            return Ok(OTPVerifyRequest {
+                sid: session.sid.clone(),
+                status: PENDING_STATUS.into(),
+            });
+        }
+
+        state.sessions_count += 1;
+        let sid = format!("VE{:032x}", state.sessions_count);
+        debug!(
+            "mock code via {}: {}. Session id: {}",
+            channel_name(channel),
+            self.code,
+            sid
+        );
+
+        state.sessions.insert(
+            mobile_number.into(),
+            MockSession {
+                sid: sid.clone(),
+                code: self.code.clone(),
+            },
+        );
+
+        Ok(OTPVerifyRequest {
+            sid,
+            status: PENDING_STATUS.into(),
+        })
+    }
+
+    async fn check_code(
+        &self,
+        mobile_number: &str,
+        code: &str,
+    ) -> ProviderResult<OTPVerifyResponse> {
+        let mut state = self.state.lock().unwrap();
+
+        let session = state
+            .sessions
+            .get(mobile_number)
+            .ok_or(ProviderError::Rejected(NOT_FOUND_STATUS_CODE))?;
+
+        if session.code != code {
+            return Ok(OTPVerifyResponse {
+                status: PENDING_STATUS.into(),
+                sid: session.sid.clone(),
+            });
+        }
+
+        // an approved session is completed and can't be checked again
+        let session = state
+            .sessions
+            .remove(mobile_number)
+            .expect("checked session exists");
+        Ok(OTPVerifyResponse {
+            status: APPROVED_STATUS.into(),
+            sid: session.sid,
+        })
+    }
+
+    async fn cancel_session(&self, sid: &str) -> ProviderResult<()> {
+        let mut state = self.state.lock().unwrap();
+
+        let number = state
+            .sessions
+            .iter()
+            .find(|(_, session)| session.sid == sid)
+            .map(|(number, _)| number.clone())
+            .ok_or(ProviderError::Rejected(NOT_FOUND_STATUS_CODE))?;
+
+        state.sessions.remove(&number);
+        info!("mock session {} canceled", sid);
+        Ok(())
+    }
+}
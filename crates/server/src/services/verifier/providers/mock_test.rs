@@ -0,0 +1,128 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::providers::mock::MockProvider;
+use crate::services::verifier::providers::{ProviderError, VerificationProvider, APPROVED_STATUS};
+use base::karma_coin::karma_coin_verifier::VerificationChannel;
+
+const CODE: &str = "123456";
+const MOBILE_NUMBER: &str = "+14155552671";
+const OTHER_MOBILE_NUMBER: &str = "+447911123456";
+
+fn provider() -> MockProvider {
+    MockProvider::new(CODE.into())
+}
+
+#[tokio::test]
+async fn send_code_creates_session() {
+    let provider = provider();
+    let session = provider
+        .send_code(MOBILE_NUMBER, VerificationChannel::Sms)
+        .await
+        .unwrap();
+    assert_eq!(session.status, "pending");
+    assert!(session.sid.starts_with("VE"));
+
+    let other_session = provider
+        .send_code(OTHER_MOBILE_NUMBER, VerificationChannel::Sms)
+        .await
+        .unwrap();
+    assert_ne!(other_session.sid, session.sid);
+}
+
+#[tokio::test]
+async fn send_code_unspecified_channel() {
+    let result = provider()
+        .send_code(MOBILE_NUMBER, VerificationChannel::Unspecified)
+        .await;
+    assert!(matches!(
+        result,
+        Err(ProviderError::UnsupportedChannel(
+            VerificationChannel::Unspecified
+        ))
+    ));
+}
+
+#[tokio::test]
+async fn resend_reuses_pending_session() {
+    let provider = provider();
+    let session = provider
+        .send_code(MOBILE_NUMBER, VerificationChannel::Sms)
+        .await
+        .unwrap();
+    let resent = provider
+        .send_code(MOBILE_NUMBER, VerificationChannel::Whatsapp)
+        .await
+        .unwrap();
+    assert_eq!(resent.sid, session.sid);
+}
+
+#[tokio::test]
+async fn wrong_code_keeps_session_pending() {
+    let provider = provider();
+    let session = provider
+        .send_code(MOBILE_NUMBER, VerificationChannel::Sms)
+        .await
+        .unwrap();
+
+    let response = provider.check_code(MOBILE_NUMBER, "654321").await.unwrap();
+    assert_eq!(response.status, "pending");
+    assert_eq!(response.sid, session.sid);
+
+    // the session can still be approved with the right code
+    let response = provider.check_code(MOBILE_NUMBER, CODE).await.unwrap();
+    assert_eq!(response.status, APPROVED_STATUS);
+}
+
+#[tokio::test]
+async fn approved_session_cant_be_checked_again() {
+    let provider = provider();
+    let session = provider
+        .send_code(MOBILE_NUMBER, VerificationChannel::Sms)
+        .await
+        .unwrap();
+
+    let response = provider.check_code(MOBILE_NUMBER, CODE).await.unwrap();
+    assert_eq!(response.status, APPROVED_STATUS);
+    assert_eq!(response.sid, session.sid);
+
+    assert!(matches!(
+        provider.check_code(MOBILE_NUMBER, CODE).await,
+        Err(ProviderError::Rejected(404))
+    ));
+
+    // a new code request creates a new session
+    let new_session = provider
+        .send_code(MOBILE_NUMBER, VerificationChannel::Sms)
+        .await
+        .unwrap();
+    assert_ne!(new_session.sid, session.sid);
+}
+
+#[tokio::test]
+async fn check_code_without_session() {
+    assert!(matches!(
+        provider().check_code(MOBILE_NUMBER, CODE).await,
+        Err(ProviderError::Rejected(404))
+    ));
+}
+
+#[tokio::test]
+async fn cancel_session() {
+    let provider = provider();
+    let session = provider
+        .send_code(MOBILE_NUMBER, VerificationChannel::Sms)
+        .await
+        .unwrap();
+
+    provider.cancel_session(&session.sid).await.unwrap();
+    assert!(matches!(
+        provider.check_code(MOBILE_NUMBER, CODE).await,
+        Err(ProviderError::Rejected(404))
+    ));
+    assert!(matches!(
+        provider.cancel_session(&session.sid).await,
+        Err(ProviderError::Rejected(404))
+    ));
+}
@@ -0,0 +1,250 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Verification providers deliver one-time codes to users' mobile numbers and check the codes users submit back.
+//! The verifier service only talks to providers via the VerificationProvider trait so new providers can be added without changing the api handlers.
+
+pub(crate) mod mock;
+pub(crate) mod twilio;
+
+#[cfg(test)]
+mod mock_test;
+
+use crate::services::verifier::channels::{channel_from_name, channel_name, ChannelsConfig};
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::VerificationChannel;
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_FAILOVER_CONFIG_KEY, VERIFIER_PROVIDER_CONFIG_KEY,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A verification session created by a provider when a code is sent to a number
+#[derive(Deserialize, Debug, Clone)]
+pub struct OTPVerifyRequest {
+    pub sid: String,
+    pub status: String,
+}
+
+/// A provider's result of checking a user provided code
+#[derive(Deserialize, Debug, Clone)]
+pub struct OTPVerifyResponse {
+    pub status: String,
+    pub sid: String,
+}
+
+/// Provider status of a verification session which was approved
+pub(crate) const APPROVED_STATUS: &str = "approved";
+
+/// Errors returned by verification providers
+#[derive(Debug, Clone)]
+pub(crate) enum ProviderError {
+    /// Provider api could not be called
+    Transport(String),
+    /// Provider api returned an unexpected http status code
+    Rejected(u16),
+    /// Provider api response could not be parsed
+    InvalidResponse(String),
+    /// Provider can't deliver codes on the channel
+    UnsupportedChannel(VerificationChannel),
+}
+
+impl fmt::Display for ProviderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProviderError::Transport(e) => write!(f, "provider api call failed: {}", e),
+            ProviderError::Rejected(status) => {
+                write!(f, "provider api returned status code: {}", status)
+            }
+            ProviderError::InvalidResponse(e) => write!(f, "invalid provider api response: {}", e),
+            ProviderError::UnsupportedChannel(channel) => {
+                write!(f, "unsupported channel: {}", channel_name(*channel))
+            }
+        }
+    }
+}
+
+impl ProviderError {
+    /// Returns true when another route may be tried after this error. Only transport errors, 5xx responses
+    /// and unsupported channels fail over - the code may have been sent on other errors, and 4xx responses
+    /// such as an invalid number would be rejected by every provider.
+    pub(crate) fn is_retryable(&self) -> bool {
+        match self {
+            ProviderError::Transport(_) | ProviderError::UnsupportedChannel(_) => true,
+            ProviderError::Rejected(status) => *status >= 500,
+            ProviderError::InvalidResponse(_) => false,
+        }
+    }
+}
+
+pub(crate) type ProviderResult<T> = std::result::Result<T, ProviderError>;
+
+/// A one-time-password verification provider
+#[async_trait::async_trait]
+pub(crate) trait VerificationProvider: Send + Sync {
+    /// Returns the provider name as used in the server config
+    fn name(&self) -> &'static str;
+
+    /// Sends a new verification code to a mobile number via a channel and returns the created session
+    async fn send_code(
+        &self,
+        mobile_number: &str,
+        channel: VerificationChannel,
+    ) -> ProviderResult<OTPVerifyRequest>;
+
+    /// Checks a user provided code for a mobile number.
+    /// Returns the session status - a code is valid only when status is approved.
+    async fn check_code(
+        &self,
+        mobile_number: &str,
+        code: &str,
+    ) -> ProviderResult<OTPVerifyResponse>;
+
+    /// Cancels a pending verification session so its code can't be used anymore
+    async fn cancel_session(&self, sid: &str) -> ProviderResult<()>;
+}
+
+/// Creates a provider by its configured name.
+/// Returns an error for unknown providers or when the provider's config is incomplete.
+async fn create_provider(name: &str) -> Result<Arc<dyn VerificationProvider>> {
+    match name {
+        twilio::TWILIO_PROVIDER_NAME => Ok(Arc::new(twilio::TwilioProvider::from_config().await?)),
+        mock::MOCK_PROVIDER_NAME => Ok(Arc::new(mock::MockProvider::from_config().await?)),
+        _ => Err(anyhow!("unsupported verification provider: {}", name)),
+    }
+}
+
+/// A failover route as defined in the server config
+#[derive(Deserialize, Debug, Clone)]
+struct FailoverRouteConfig {
+    provider: String,
+    /// channel to use instead of the requested one
+    channel: Option<String>,
+}
+
+/// A provider and the channel a code is sent with
+#[derive(Clone)]
+pub(crate) struct ProviderRoute {
+    pub(crate) provider: Arc<dyn VerificationProvider>,
+    pub(crate) channel: VerificationChannel,
+}
+
+/// The verifier's providers - a primary provider and ordered failover routes
+/// which are used when sending a code with the primary provider fails.
+#[derive(Default)]
+pub(crate) struct Providers {
+    /// all configured providers by name
+    providers: HashMap<String, Arc<dyn VerificationProvider>>,
+    primary: Option<Arc<dyn VerificationProvider>>,
+    failover_routes: Vec<FailoverRoute>,
+}
+
+/// A failover route with a resolved provider
+struct FailoverRoute {
+    provider: Arc<dyn VerificationProvider>,
+    /// None to use the requested channel
+    channel: Option<VerificationChannel>,
+}
+
+impl Providers {
+    /// Creates the primary and failover providers from the server config.
+    /// Returns an error if a provider can't be created or a failover channel isn't enabled.
+    pub(crate) async fn from_config(channels: &ChannelsConfig) -> Result<Self> {
+        let mut providers = Providers::default();
+
+        let primary_name = ServerConfigService::get(VERIFIER_PROVIDER_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+        providers.primary = Some(providers.get_or_create(&primary_name).await?);
+        info!("Verification provider: {}", primary_name);
+
+        let routes_config = ServerConfigService::get_typed::<Vec<FailoverRouteConfig>>(
+            VERIFIER_FAILOVER_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap_or_default();
+
+        for route_config in routes_config {
+            let provider = providers.get_or_create(&route_config.provider).await?;
+            let channel = match route_config.channel {
+                Some(name) => {
+                    let channel = channel_from_name(&name)?;
+                    if !channels.is_enabled(channel) {
+                        return Err(anyhow!(
+                            "failover channel {} is not enabled",
+                            channel_name(channel)
+                        ));
+                    }
+                    Some(channel)
+                }
+                None => None,
+            };
+
+            info!(
+                "Verification failover route: {} {}",
+                route_config.provider,
+                channel.map_or("requested channel", channel_name)
+            );
+
+            providers
+                .failover_routes
+                .push(FailoverRoute { provider, channel });
+        }
+
+        Ok(providers)
+    }
+
+    /// Returns a configured provider or creates it if it wasn't created yet
+    async fn get_or_create(&mut self, name: &str) -> Result<Arc<dyn VerificationProvider>> {
+        if let Some(provider) = self.providers.get(name) {
+            return Ok(Arc::clone(provider));
+        }
+
+        let provider = create_provider(name).await?;
+        self.providers.insert(name.into(), Arc::clone(&provider));
+        Ok(provider)
+    }
+
+    /// Returns the primary provider
+    pub(crate) fn primary(&self) -> Arc<dyn VerificationProvider> {
+        Arc::clone(
+            self.primary
+                .as_ref()
+                .expect("verification provider not initialized"),
+        )
+    }
+
+    /// Returns a provider by its name
+    pub(crate) fn get(&self, name: &str) -> Option<Arc<dyn VerificationProvider>> {
+        self.providers.get(name).map(Arc::clone)
+    }
+
+    /// Returns the routes to try, in order, when sending a code on the requested channel.
+    /// The first route is the primary provider with the requested channel.
+    pub(crate) fn send_routes(&self, channel: VerificationChannel) -> Vec<ProviderRoute> {
+        let mut routes = vec![ProviderRoute {
+            provider: self.primary(),
+            channel,
+        }];
+
+        for failover_route in &self.failover_routes {
+            let route = ProviderRoute {
+                provider: Arc::clone(&failover_route.provider),
+                channel: failover_route.channel.unwrap_or(channel),
+            };
+
+            // skip routes which were already tried
+            if !routes
+                .iter()
+                .any(|r| r.provider.name() == route.provider.name() && r.channel == route.channel)
+            {
+                routes.push(route);
+            }
+        }
+
+        routes
+    }
+}
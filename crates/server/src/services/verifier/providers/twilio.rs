@@ -0,0 +1,149 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::providers::{
+    OTPVerifyRequest, OTPVerifyResponse, ProviderError, ProviderResult, VerificationProvider,
+};
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_verifier::VerificationChannel;
+use base::server_config_service::{
+    ServerConfigService, TWILIO_ACCOUNT_SID_CONFIG_KEY, TWILIO_AUTH_TOKEN_CONFIG_KEY,
+    TWILIO_BASE_URL_CONFIG_KEY, TWILIO_SERVICE_ID_CONFIG_KEY, TWILIO_TIMEOUT_SECS_CONFIG_KEY,
+};
+use http::StatusCode;
+use reqwest::{Client, Response};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub(crate) const TWILIO_PROVIDER_NAME: &str = "twilio";
+
+/// Twilio Verify api provider
+pub(crate) struct TwilioProvider {
+    /// Verify api base url. Configurable so the provider can be tested against a local stub
+    base_url: String,
+    account_sid: String,
+    service_id: String,
+    auth_token: String,
+    client: Client,
+}
+
+impl TwilioProvider {
+    /// Creates a provider from the twilio section of the server config
+    pub(crate) async fn from_config() -> Result<Self> {
+        let timeout_secs = ServerConfigService::get_u64(TWILIO_TIMEOUT_SECS_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+
+        let client = Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            .build()?;
+
+        Ok(TwilioProvider {
+            base_url: get_config_value(TWILIO_BASE_URL_CONFIG_KEY).await?,
+            account_sid: get_config_value(TWILIO_ACCOUNT_SID_CONFIG_KEY).await?,
+            service_id: get_config_value(TWILIO_SERVICE_ID_CONFIG_KEY).await?,
+            auth_token: get_config_value(TWILIO_AUTH_TOKEN_CONFIG_KEY).await?,
+            client,
+        })
+    }
+
+    /// Posts a form to a Twilio verify service endpoint
+    async fn post_form(
+        &self,
+        endpoint: &str,
+        form_body: &HashMap<&str, &str>,
+    ) -> ProviderResult<Response> {
+        let url = format!(
+            "{api_url}/v2/Services/{serv_id}/{endpoint}",
+            api_url = self.base_url.trim_end_matches('/'),
+            serv_id = self.service_id,
+            endpoint = endpoint,
+        );
+
+        self.client
+            .post(url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(form_body)
+            .send()
+            .await
+            .map_err(|e| ProviderError::Transport(e.to_string()))
+    }
+}
+
+#[async_trait::async_trait]
+impl VerificationProvider for TwilioProvider {
+    fn name(&self) -> &'static str {
+        TWILIO_PROVIDER_NAME
+    }
+
+    async fn send_code(
+        &self,
+        mobile_number: &str,
+        channel: VerificationChannel,
+    ) -> ProviderResult<OTPVerifyRequest> {
+        let twilio_channel = match channel {
+            VerificationChannel::Sms => "sms",
+            VerificationChannel::Voice => "call",
+            VerificationChannel::Whatsapp => "whatsapp",
+            VerificationChannel::Unspecified => {
+                return Err(ProviderError::UnsupportedChannel(channel))
+            }
+        };
+
+        let mut form_body: HashMap<&str, &str> = HashMap::new();
+        form_body.insert("To", mobile_number);
+        form_body.insert("Channel", twilio_channel);
+
+        let response = self.post_form("Verifications", &form_body).await?;
+        if response.status() != StatusCode::CREATED {
+            return Err(ProviderError::Rejected(response.status().as_u16()));
+        }
+
+        response
+            .json::<OTPVerifyRequest>()
+            .await
+            .map_err(|e| ProviderError::InvalidResponse(e.to_string()))
+    }
+
+    async fn check_code(
+        &self,
+        mobile_number: &str,
+        code: &str,
+    ) -> ProviderResult<OTPVerifyResponse> {
+        let mut form_body: HashMap<&str, &str> = HashMap::new();
+        form_body.insert("To", mobile_number);
+        form_body.insert("Code", code);
+
+        let response = self.post_form("VerificationCheck", &form_body).await?;
+        if response.status() != StatusCode::OK {
+            return Err(ProviderError::Rejected(response.status().as_u16()));
+        }
+
+        response
+            .json::<OTPVerifyResponse>()
+            .await
+            .map_err(|e| ProviderError::InvalidResponse(e.to_string()))
+    }
+
+    async fn cancel_session(&self, sid: &str) -> ProviderResult<()> {
+        let mut form_body: HashMap<&str, &str> = HashMap::new();
+        form_body.insert("Status", "canceled");
+
+        let response = self
+            .post_form(&format!("Verifications/{}", sid), &form_body)
+            .await?;
+        if response.status() != StatusCode::OK {
+            return Err(ProviderError::Rejected(response.status().as_u16()));
+        }
+
+        Ok(())
+    }
+}
+
+/// Helper to read a required twilio config value
+async fn get_config_value(key: &str) -> Result<String> {
+    ServerConfigService::get(key.into())
+        .await?
+        .ok_or_else(|| anyhow!("missing twilio config value: {}", key))
+}
@@ -0,0 +1,206 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Token bucket rate limits of the verifier api by phone number, account and client ip.
+//! Each key gets a bucket of `capacity` tokens which refills over `window_secs`. A request takes one token.
+
+use anyhow::{anyhow, Result};
+use base::server_config_service::{
+    ServerConfigService, RATE_LIMIT_ACCOUNT_CAPACITY_CONFIG_KEY,
+    RATE_LIMIT_ACCOUNT_WINDOW_SECS_CONFIG_KEY, RATE_LIMIT_CLIENT_IP_CAPACITY_CONFIG_KEY,
+    RATE_LIMIT_CLIENT_IP_WINDOW_SECS_CONFIG_KEY, RATE_LIMIT_PHONE_NUMBER_CAPACITY_CONFIG_KEY,
+    RATE_LIMIT_PHONE_NUMBER_WINDOW_SECS_CONFIG_KEY,
+};
+use chrono::Utc;
+use sp_core::crypto::AccountId32;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::net::IpAddr;
+use std::time::Duration;
+
+/// Error returned by api handlers when a request exceeds a rate limit.
+/// The grpc layer maps it to a RESOURCE_EXHAUSTED status with retry-after metadata.
+#[derive(Debug, Clone)]
+pub(crate) struct RateLimitExceeded {
+    /// name of the exceeded limit
+    pub(crate) limit: &'static str,
+    /// time until the request may be retried
+    pub(crate) retry_after: Duration,
+}
+
+impl fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} rate limit exceeded. retry after {} secs",
+            self.limit,
+            self.retry_after.as_secs()
+        )
+    }
+}
+
+impl std::error::Error for RateLimitExceeded {}
+
+/// A bucket of request tokens
+struct TokenBucket {
+    tokens: f64,
+    /// time tokens were last refilled at in milliseconds
+    updated_at: u64,
+}
+
+/// Token buckets of one limit by key
+pub(crate) struct RateLimiter<K: Hash + Eq> {
+    name: &'static str,
+    /// tokens of a full bucket. 0 disables the limit
+    capacity: u64,
+    /// time to refill an empty bucket in milliseconds
+    window: u64,
+    buckets: HashMap<K, TokenBucket>,
+    /// time idle buckets were last pruned at in milliseconds
+    pruned_at: u64,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    /// Creates a limiter of `capacity` requests per `window` milliseconds
+    pub(crate) fn new(name: &'static str, capacity: u64, window: u64) -> Self {
+        RateLimiter {
+            name,
+            capacity,
+            window,
+            buckets: HashMap::new(),
+            pruned_at: 0,
+        }
+    }
+
+    /// Creates a limiter from its capacity and window config values
+    async fn from_config(
+        name: &'static str,
+        capacity_key: &str,
+        window_secs_key: &str,
+    ) -> Result<Self> {
+        let capacity = ServerConfigService::get_u64(capacity_key.into())
+            .await?
+            .unwrap();
+        let window_secs = ServerConfigService::get_u64(window_secs_key.into())
+            .await?
+            .unwrap();
+
+        let window = window_secs
+            .checked_mul(1000)
+            .ok_or_else(|| anyhow!("{} is too large: {}", window_secs_key, window_secs))?;
+
+        info!(
+            "{} rate limit: {} requests per {} secs",
+            name, capacity, window_secs
+        );
+
+        Ok(RateLimiter::new(name, capacity, window))
+    }
+
+    /// Takes a token from the key's bucket.
+    /// Returns an error with the time until a token is available when the bucket is empty.
+    pub(crate) fn check(&mut self, key: K) -> Result<(), RateLimitExceeded> {
+        if self.capacity == 0 {
+            return Ok(());
+        }
+
+        let now = Utc::now().timestamp_millis() as u64;
+        let capacity = self.capacity as f64;
+        // tokens refilled per millisecond
+        let rate = capacity / self.window.max(1) as f64;
+
+        self.prune(now);
+
+        let bucket = self.buckets.entry(key).or_insert(TokenBucket {
+            tokens: capacity,
+            updated_at: now,
+        });
+
+        let elapsed = now.saturating_sub(bucket.updated_at) as f64;
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        let retry_after_ms = ((1.0 - bucket.tokens) / rate).ceil() as u64;
+        Err(RateLimitExceeded {
+            limit: self.name,
+            retry_after: Duration::from_millis(retry_after_ms),
+        })
+    }
+
+    /// Removes buckets which were refilled since their last use as they don't need to be kept.
+    /// Runs at most once per window so checking a request doesn't scan all buckets.
+    fn prune(&mut self, now: u64) {
+        let window = self.window;
+        if now.saturating_sub(self.pruned_at) < window {
+            return;
+        }
+
+        self.buckets
+            .retain(|_, bucket| now.saturating_sub(bucket.updated_at) < window);
+        self.pruned_at = now;
+    }
+}
+
+/// The verifier's rate limits
+pub(crate) struct RateLimits {
+    /// SendVerificationCode requests by normalized phone number
+    pub(crate) phone_number: RateLimiter<String>,
+    /// VerifyNumber requests by account
+    pub(crate) account: RateLimiter<AccountId32>,
+    /// requests of all apis by client ip
+    pub(crate) client_ip: RateLimiter<IpAddr>,
+}
+
+impl Default for RateLimits {
+    fn default() -> Self {
+        RateLimits {
+            phone_number: RateLimiter::new("phone number", 0, 0),
+            account: RateLimiter::new("account", 0, 0),
+            client_ip: RateLimiter::new("client ip", 0, 0),
+        }
+    }
+}
+
+impl RateLimits {
+    /// Loads the rate limits from the server config
+    pub(crate) async fn from_config() -> Result<Self> {
+        Ok(RateLimits {
+            phone_number: RateLimiter::from_config(
+                "phone number",
+                RATE_LIMIT_PHONE_NUMBER_CAPACITY_CONFIG_KEY,
+                RATE_LIMIT_PHONE_NUMBER_WINDOW_SECS_CONFIG_KEY,
+            )
+            .await?,
+            account: RateLimiter::from_config(
+                "account",
+                RATE_LIMIT_ACCOUNT_CAPACITY_CONFIG_KEY,
+                RATE_LIMIT_ACCOUNT_WINDOW_SECS_CONFIG_KEY,
+            )
+            .await?,
+            client_ip: RateLimiter::from_config(
+                "client ip",
+                RATE_LIMIT_CLIENT_IP_CAPACITY_CONFIG_KEY,
+                RATE_LIMIT_CLIENT_IP_WINDOW_SECS_CONFIG_KEY,
+            )
+            .await?,
+        })
+    }
+
+    /// Checks the client ip limit when the client's address is known
+    pub(crate) fn check_client_ip(
+        &mut self,
+        client_ip: Option<IpAddr>,
+    ) -> Result<(), RateLimitExceeded> {
+        match client_ip {
+            Some(ip) => self.client_ip.check(ip),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,71 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Protection against replayed verify number requests.
+//! Requests must be signed within a time window of the verifier's clock and each signed request is accepted once.
+
+use anyhow::{anyhow, Result};
+use base::server_config_service::{ServerConfigService, VERIFIER_REQUEST_MAX_AGE_SECS_CONFIG_KEY};
+use chrono::Utc;
+use std::collections::HashMap;
+
+/// Reasons a request is rejected as a replay
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ReplayError {
+    Stale,
+    Duplicate,
+}
+
+/// Tracks requests seen within the freshness window
+#[derive(Default)]
+pub(crate) struct ReplayGuard {
+    /// maximum difference between a request's timestamp and the current time in milliseconds
+    max_age: u64,
+    /// hashes of seen requests and their timestamps
+    seen_requests: HashMap<[u8; 32], u64>,
+}
+
+impl ReplayGuard {
+    /// Creates a guard with the freshness window from the server config
+    pub(crate) async fn from_config() -> Result<Self> {
+        let max_age_secs =
+            ServerConfigService::get_u64(VERIFIER_REQUEST_MAX_AGE_SECS_CONFIG_KEY.into())
+                .await?
+                .unwrap();
+
+        let max_age = max_age_secs.checked_mul(1000).ok_or_else(|| {
+            anyhow!(
+                "{} is too large: {}",
+                VERIFIER_REQUEST_MAX_AGE_SECS_CONFIG_KEY,
+                max_age_secs
+            )
+        })?;
+
+        Ok(ReplayGuard {
+            max_age,
+            seen_requests: HashMap::new(),
+        })
+    }
+
+    /// Accepts a signed request with the provided timestamp in milliseconds once.
+    /// `data` is the signed request data.
+    pub(crate) fn check(&mut self, data: &[u8], timestamp: u64) -> Result<(), ReplayError> {
+        let now = Utc::now().timestamp_millis() as u64;
+        if now.abs_diff(timestamp) > self.max_age {
+            return Err(ReplayError::Stale);
+        }
+
+        // requests outside of the window are rejected as stale so they don't need to be kept
+        let max_age = self.max_age;
+        self.seen_requests
+            .retain(|_, seen_timestamp| now.abs_diff(*seen_timestamp) <= max_age);
+
+        let hash = sp_core::hashing::blake2_256(data);
+        if self.seen_requests.insert(hash, timestamp).is_some() {
+            return Err(ReplayError::Duplicate);
+        }
+
+        Ok(())
+    }
+}
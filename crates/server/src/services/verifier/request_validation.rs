@@ -0,0 +1,120 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::phone_number::{normalize_phone_number, NormalizedPhoneNumber};
+use base::karma_coin::karma_coin_verifier::{
+    VerificationResult, VerifyNumberRequest, VerifyNumberRequestData,
+};
+use prost::Message;
+use sp_core::{
+    crypto::{AccountId32, Ss58Codec},
+    ed25519::{Pair, Public, Signature},
+    Pair as PairT,
+};
+use std::fmt;
+
+/// Reasons a verify number request is invalid
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum ValidationError {
+    /// request data is not a VerifyNumberRequestData message
+    MalformedData,
+    MissingAccountId,
+    MissingUserName,
+    MissingPhoneNumber,
+    InvalidPhoneNumber(String),
+    /// account id is not an ss58 address
+    InvalidAccountId,
+    /// account id is an address of another network
+    UnexpectedNetworkPrefix(u16),
+    /// signature is not an ed25519 signature
+    MalformedSignature,
+    /// signature is not the account's signature of the request data
+    InvalidSignature,
+}
+
+impl ValidationError {
+    /// Returns the api result of a request which failed validation
+    pub(crate) fn result(&self) -> VerificationResult {
+        match self {
+            ValidationError::MalformedSignature | ValidationError::InvalidSignature => {
+                VerificationResult::InvalidSignature
+            }
+            _ => VerificationResult::MissingData,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::MalformedData => write!(f, "malformed request data"),
+            ValidationError::MissingAccountId => write!(f, "missing account id"),
+            ValidationError::MissingUserName => write!(f, "missing user name"),
+            ValidationError::MissingPhoneNumber => write!(f, "missing phone number"),
+            ValidationError::InvalidPhoneNumber(e) => write!(f, "invalid phone number: {}", e),
+            ValidationError::InvalidAccountId => write!(f, "invalid account id"),
+            ValidationError::UnexpectedNetworkPrefix(prefix) => {
+                write!(f, "unexpected account id network prefix: {}", prefix)
+            }
+            ValidationError::MalformedSignature => write!(f, "malformed signature"),
+            ValidationError::InvalidSignature => write!(f, "invalid signature"),
+        }
+    }
+}
+
+/// A verify number request signed by its account
+pub(crate) struct ValidatedRequest {
+    pub(crate) data: VerifyNumberRequestData,
+    pub(crate) account_id: AccountId32,
+    pub(crate) phone_number: NormalizedPhoneNumber,
+}
+
+/// Decodes and validates a verify number request and verifies its signature.
+/// When `ss58_prefix` is provided only account ids with this network prefix are accepted.
+pub(crate) fn validate_request(
+    req: &VerifyNumberRequest,
+    ss58_prefix: Option<u16>,
+) -> Result<ValidatedRequest, ValidationError> {
+    let data = VerifyNumberRequestData::decode(req.data.as_ref())
+        .map_err(|_| ValidationError::MalformedData)?;
+
+    if data.account_id.is_empty() {
+        return Err(ValidationError::MissingAccountId);
+    }
+
+    if data.user_name.is_empty() {
+        return Err(ValidationError::MissingUserName);
+    }
+
+    if data.phone_number.is_empty() {
+        return Err(ValidationError::MissingPhoneNumber);
+    }
+
+    let phone_number = normalize_phone_number(&data.phone_number)
+        .map_err(|e| ValidationError::InvalidPhoneNumber(e.to_string()))?;
+
+    let (account_id, format) = AccountId32::from_ss58check_with_version(&data.account_id)
+        .map_err(|_| ValidationError::InvalidAccountId)?;
+
+    if let Some(expected_prefix) = ss58_prefix {
+        let prefix = u16::from(format);
+        if prefix != expected_prefix {
+            return Err(ValidationError::UnexpectedNetworkPrefix(prefix));
+        }
+    }
+
+    let signature =
+        Signature::from_slice(req.signature.as_ref()).ok_or(ValidationError::MalformedSignature)?;
+
+    let pub_key = Public::from_raw(*account_id.as_ref());
+    if !Pair::verify(&signature, &req.data, &pub_key) {
+        return Err(ValidationError::InvalidSignature);
+    }
+
+    Ok(ValidatedRequest {
+        data,
+        account_id,
+        phone_number,
+    })
+}
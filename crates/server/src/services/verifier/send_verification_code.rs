@@ -2,24 +2,24 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::verifier::channels::channel_name;
+use crate::services::verifier::phone_number::normalize_phone_number;
+use crate::services::verifier::providers::ProviderError;
 use crate::services::verifier::verifier_service::VerifierService;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use base::karma_coin::karma_coin_verifier::{
     SendVerificationCodeRequest, SendVerificationCodeResponse, SendVerificationCodeResult,
+    VerificationChannel,
 };
-use http::{header, StatusCode};
-use reqwest::Client;
-use serde::Deserialize;
-use std::collections::HashMap;
+use prost::Message;
+use std::net::IpAddr;
 use xactor::*;
 
 #[message(result = "Result<SendVerificationCodeResponse>")]
-pub(crate) struct SendVerificationCode(pub SendVerificationCodeRequest);
-
-#[derive(Deserialize, Debug, Clone)]
-pub struct OTPVerifyRequest {
-    pub sid: String,
-    pub status: String,
+pub(crate) struct SendVerificationCode {
+    pub(crate) request: SendVerificationCodeRequest,
+    /// address of the calling client - used for rate limiting
+    pub(crate) client_ip: Option<IpAddr>,
 }
 
 /// Request to complete verification and sign up
@@ -30,94 +30,167 @@ impl Handler<SendVerificationCode> for VerifierService {
         _ctx: &mut Context<Self>,
         msg: SendVerificationCode,
     ) -> Result<SendVerificationCodeResponse> {
-        let req = msg.0;
+        let mut req = msg.request;
+
+        self.rate_limits.check_client_ip(msg.client_ip)?;
+
+        // use the request encrypted to the verifier's pre-key bundle when one is provided
+        if let Some(payload) = req.encrypted_request.take() {
+            if !req.mobile_number.is_empty() {
+                return Ok(create_response(
+                    SendVerificationCodeResult::InvalidUserData,
+                    Some("Mobile number must be encrypted".into()),
+                    None,
+                    VerificationChannel::Unspecified,
+                ));
+            }
 
+            req = match self.decrypt_payload(payload).await.and_then(|data| {
+                let request = SendVerificationCodeRequest::decode(data.as_slice())?;
+                match request.encrypted_request {
+                    Some(_) => Err(anyhow!("nested encrypted request")),
+                    None => Ok(request),
+                }
+            }) {
+                Ok(request) => request,
+                Err(e) => {
+                    info!("failed to decrypt request: {}", e);
+                    return Ok(create_response(
+                        SendVerificationCodeResult::InvalidEncryption,
+                        Some("Failed to decrypt request".into()),
+                        None,
+                        VerificationChannel::Unspecified,
+                    ));
+                }
+            };
+        }
+
+        // mobile numbers are never logged
+        info!("send verification code called");
         let number = req.mobile_number.clone();
-        info!("sending verification code to: {}", number);
 
         if number.is_empty() {
             return Ok(create_response(
                 SendVerificationCodeResult::InvalidUserData,
                 Some("Missing mobile number".into()),
                 None,
+                VerificationChannel::Unspecified,
+            ));
+        }
+
+        let number = match normalize_phone_number(&number) {
+            Ok(number) => number,
+            Err(e) => {
+                info!("invalid mobile number: {}", e);
+                return Ok(create_response(
+                    SendVerificationCodeResult::InvalidUserData,
+                    Some("Invalid mobile number".into()),
+                    None,
+                    VerificationChannel::Unspecified,
+                ));
+            }
+        };
+
+        if !self.countries.is_allowed(number.country_code) {
+            info!("country code {} is not allowed", number.country_code);
+            return Ok(create_response(
+                SendVerificationCodeResult::RegionNotSupported,
+                Some("Mobile number region is not supported".into()),
+                None,
+                VerificationChannel::Unspecified,
             ));
         }
+        let number = number.number;
+
+        let channel = match VerificationChannel::from_i32(req.channel) {
+            Some(VerificationChannel::Unspecified) => self.channels.default_channel,
+            Some(channel) => channel,
+            None => {
+                return Ok(create_response(
+                    SendVerificationCodeResult::InvalidUserData,
+                    Some("Unknown delivery channel".into()),
+                    None,
+                    VerificationChannel::Unspecified,
+                ));
+            }
+        };
+
+        if !self.channels.is_enabled(channel) {
+            info!("channel {} is not enabled", channel_name(channel));
+            return Ok(create_response(
+                SendVerificationCodeResult::ChannelNotSupported,
+                Some(format!(
+                    "Delivery channel {} is not supported",
+                    channel_name(channel)
+                )),
+                None,
+                channel,
+            ));
+        }
+
+        // only requests which may send a code use up the number's quota
+        self.rate_limits.phone_number.check(number.clone())?;
 
-        let url = format!(
-            "https://verify.twilio.com/v2/Services/{serv_id}/Verifications",
-            serv_id = self.twilio_service_id.as_ref().unwrap(),
-        );
-
-        let whatsapp: String = "whatsapp".to_string();
-
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            "Content-Type",
-            "application/x-www-form-urlencoded".parse().unwrap(),
-        );
-
-        let mut form_body: HashMap<&str, &String> = HashMap::new();
-        form_body.insert("To", &number);
-        form_body.insert("Channel", &whatsapp);
-
-        let client = Client::new();
-        let res = client
-            .post(url)
-            .basic_auth(
-                self.twilio_account_id.as_ref().unwrap(),
-                Some(self.twilio_token.as_ref().unwrap()),
-            )
-            .headers(headers)
-            .form(&form_body)
-            .send()
-            .await;
-
-        return match res {
-            Ok(response) => {
-                if response.status() != StatusCode::CREATED {
+        // try the primary provider first and fall back to the failover routes on transport and 5xx errors
+        let mut last_error = None;
+        for route in self.providers.send_routes(channel) {
+            let provider_name = route.provider.name();
+            match route.provider.send_code(&number, route.channel).await {
+                Ok(result) => {
                     info!(
-                        "failed. twilio response status code != 201: {}",
-                        response.status()
+                        "Sent verification code via {} {}. Session id: {}. Status: {}",
+                        provider_name,
+                        channel_name(route.channel),
+                        result.sid,
+                        result.status
                     );
+                    self.sessions.insert(&result.sid, &number, provider_name);
                     return Ok(create_response(
-                        SendVerificationCodeResult::Failed,
-                        Some("Code verifier failed to send".into()),
+                        SendVerificationCodeResult::Sent,
                         None,
+                        Some(result.sid),
+                        route.channel,
                     ));
                 }
-
-                let data = response.json::<OTPVerifyRequest>().await;
-                return match data {
-                    Ok(result) => {
-                        info!(
-                            "Send verification code via whatsapp. Session id: {}. Status: {}",
-                            result.sid, result.status
-                        );
-                        Ok(create_response(
-                            SendVerificationCodeResult::Sent,
-                            None,
-                            Some(result.sid),
-                        ))
-                    }
-                    Err(e) => {
-                        info!("error parsing twilio resp: {}", e);
-                        Ok(create_response(
-                            SendVerificationCodeResult::Failed,
-                            Some("Unexpected code verifier api response".into()),
-                            None,
-                        ))
+                Err(e) => {
+                    warn!(
+                        "failed to send code via {} {}: {}",
+                        provider_name,
+                        channel_name(route.channel),
+                        e
+                    );
+                    let retryable = e.is_retryable();
+                    last_error = Some(e);
+                    if !retryable {
+                        break;
                     }
-                };
-            }
-            Err(e) => {
-                info!("error calling twilio: {}", e);
-                Ok(create_response(
-                    SendVerificationCodeResult::Failed,
-                    Some("Failed to call code verifier api".into()),
-                    None,
-                ))
+                }
             }
+        }
+
+        let (result, error_message) = match last_error.expect("no send routes") {
+            ProviderError::Rejected(_) => (
+                SendVerificationCodeResult::Failed,
+                "Code verifier failed to send".into(),
+            ),
+            ProviderError::InvalidResponse(_) => (
+                SendVerificationCodeResult::Failed,
+                "Unexpected code verifier api response".into(),
+            ),
+            ProviderError::UnsupportedChannel(channel) => (
+                SendVerificationCodeResult::ChannelNotSupported,
+                format!(
+                    "Delivery channel {} is not supported",
+                    channel_name(channel)
+                ),
+            ),
+            ProviderError::Transport(_) => (
+                SendVerificationCodeResult::Failed,
+                "Failed to call code verifier api".into(),
+            ),
         };
+
+        Ok(create_response(result, Some(error_message), None, channel))
     }
 }
 
@@ -126,10 +199,12 @@ fn create_response(
     result: SendVerificationCodeResult,
     error_message: Option<String>,
     session_id: Option<String>,
+    channel: VerificationChannel,
 ) -> SendVerificationCodeResponse {
     SendVerificationCodeResponse {
         result: result as i32,
         session_id: session_id.unwrap_or("".into()),
         error_message: error_message.unwrap_or("".into()),
+        channel: channel as i32,
     }
 }
@@ -0,0 +1,175 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Verification sessions created by SendVerificationCode.
+//! VerifyNumber requests are only forwarded to a provider for a known, unexpired session issued to the request's number.
+
+use anyhow::{anyhow, Result};
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_MAX_CODE_ATTEMPTS_CONFIG_KEY,
+    VERIFIER_SESSION_TTL_SECS_CONFIG_KEY,
+};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A verification code sent to a mobile number
+pub(crate) struct VerificationSession {
+    /// provider's session id
+    pub(crate) sid: String,
+    pub(crate) mobile_number: String,
+    /// time the session was created at in milliseconds
+    pub(crate) created_at: u64,
+    /// number of wrong codes checked in this session
+    pub(crate) failed_attempts: u32,
+    /// name of the provider which issued the session
+    pub(crate) provider_name: &'static str,
+}
+
+/// Reasons a session can't be used to check a code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SessionError {
+    Unknown,
+    Expired,
+    NumberMismatch,
+    /// maximum failed attempts were made
+    Locked,
+}
+
+impl fmt::Display for SessionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SessionError::Unknown => write!(f, "unknown session"),
+            SessionError::Expired => write!(f, "session expired"),
+            SessionError::NumberMismatch => write!(f, "session was issued to another number"),
+            SessionError::Locked => write!(f, "session is locked after too many failed attempts"),
+        }
+    }
+}
+
+/// Verification sessions by session id
+#[derive(Default)]
+pub(crate) struct SessionStore {
+    sessions: HashMap<String, VerificationSession>,
+    /// time a session may be used in after it was created in milliseconds
+    ttl: u64,
+    /// failed code checks after which a session is locked
+    max_failed_attempts: u32,
+}
+
+impl SessionStore {
+    /// Creates a store with the session ttl and failed attempts limit from the server config
+    pub(crate) async fn from_config() -> Result<Self> {
+        let ttl_secs = ServerConfigService::get_u64(VERIFIER_SESSION_TTL_SECS_CONFIG_KEY.into())
+            .await?
+            .unwrap();
+
+        let max_failed_attempts =
+            ServerConfigService::get_u64(VERIFIER_MAX_CODE_ATTEMPTS_CONFIG_KEY.into())
+                .await?
+                .unwrap();
+
+        // no code could ever be checked with 0 attempts
+        if max_failed_attempts == 0 {
+            return Err(anyhow!(
+                "{} must be at least 1",
+                VERIFIER_MAX_CODE_ATTEMPTS_CONFIG_KEY
+            ));
+        }
+        let max_failed_attempts = u32::try_from(max_failed_attempts).map_err(|_| {
+            anyhow!(
+                "{} is too large: {}",
+                VERIFIER_MAX_CODE_ATTEMPTS_CONFIG_KEY,
+                max_failed_attempts
+            )
+        })?;
+
+        let ttl = ttl_secs.checked_mul(1000).ok_or_else(|| {
+            anyhow!(
+                "{} is too large: {}",
+                VERIFIER_SESSION_TTL_SECS_CONFIG_KEY,
+                ttl_secs
+            )
+        })?;
+
+        Ok(SessionStore {
+            sessions: HashMap::new(),
+            ttl,
+            max_failed_attempts,
+        })
+    }
+
+    /// Adds a session issued by a provider.
+    /// Providers may return an existing pending session when a code is resent to a number,
+    /// in which case the existing session and its failed attempts are kept.
+    pub(crate) fn insert(&mut self, sid: &str, mobile_number: &str, provider_name: &'static str) {
+        let now = Utc::now().timestamp_millis() as u64;
+        self.prune(now);
+
+        self.sessions
+            .entry(sid.into())
+            .or_insert_with(|| VerificationSession {
+                sid: sid.into(),
+                mobile_number: mobile_number.into(),
+                created_at: now,
+                failed_attempts: 0,
+                provider_name,
+            });
+    }
+
+    /// Returns the session a code for a mobile number may be checked in
+    pub(crate) fn get_valid(
+        &mut self,
+        sid: &str,
+        mobile_number: &str,
+    ) -> Result<&mut VerificationSession, SessionError> {
+        let now = Utc::now().timestamp_millis() as u64;
+        let ttl = self.ttl;
+
+        let created_at = match self.sessions.get(sid) {
+            Some(session) => session.created_at,
+            None => return Err(SessionError::Unknown),
+        };
+
+        if now.saturating_sub(created_at) >= ttl {
+            self.sessions.remove(sid);
+            return Err(SessionError::Expired);
+        }
+
+        let session = self.sessions.get_mut(sid).ok_or(SessionError::Unknown)?;
+        if session.mobile_number != mobile_number {
+            return Err(SessionError::NumberMismatch);
+        }
+
+        if session.failed_attempts >= self.max_failed_attempts {
+            return Err(SessionError::Locked);
+        }
+
+        Ok(session)
+    }
+
+    /// Counts a wrong code checked in a session.
+    /// Returns true when the session got locked by this attempt.
+    pub(crate) fn record_failed_attempt(&mut self, sid: &str) -> bool {
+        match self.sessions.get_mut(sid) {
+            Some(session) => {
+                session.failed_attempts += 1;
+                session.failed_attempts == self.max_failed_attempts
+            }
+            None => false,
+        }
+    }
+
+    /// Removes a completed session
+    pub(crate) fn remove(&mut self, sid: &str) {
+        self.sessions.remove(sid);
+    }
+
+    /// Removes expired sessions
+    fn prune(&mut self, now: u64) {
+        let ttl = self.ttl;
+        self.sessions
+            .retain(|_, session| now.saturating_sub(session.created_at) < ttl);
+    }
+}
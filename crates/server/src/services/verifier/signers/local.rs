@@ -0,0 +1,30 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::signers::Signer;
+use anyhow::Result;
+use sp_core::ed25519::{Pair, Public, Signature};
+use sp_core::Pair as PairT;
+
+/// Signs with a key pair held in the verifier process
+pub(crate) struct LocalSigner {
+    pair: Pair,
+}
+
+impl LocalSigner {
+    pub(crate) fn new(pair: Pair) -> Self {
+        LocalSigner { pair }
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for LocalSigner {
+    fn public_key(&self) -> Public {
+        self.pair.public()
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<Signature> {
+        Ok(self.pair.sign(data))
+    }
+}
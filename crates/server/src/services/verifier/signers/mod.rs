@@ -0,0 +1,72 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Signers sign verification evidence with a verifier key. The verifier service only signs via the Signer
+//! trait so key material can be kept in a keystore file or out of the verifier process in a remote signer.
+
+pub(crate) mod local;
+pub(crate) mod remote;
+
+use crate::keystore::Keystore;
+use anyhow::{anyhow, Result};
+use local::LocalSigner;
+use remote::RemoteSigner;
+use sp_core::ed25519::{Pair, Public, Signature};
+use sp_core::Pair as PairT;
+use std::path::Path;
+use std::sync::Arc;
+
+/// An ed25519 signing key
+#[async_trait::async_trait]
+pub(crate) trait Signer: Send + Sync {
+    /// Returns the public key of the signing key
+    fn public_key(&self) -> Public;
+
+    /// Signs data with the signing key
+    async fn sign(&self, data: &[u8]) -> Result<Signature>;
+}
+
+/// Where a signing key is kept
+pub(crate) enum SignerConfig {
+    /// in-process key derived from a mnemonic
+    Mnemonic {
+        mnemonic: String,
+        password: Option<String>,
+    },
+    /// in-process key unlocked from a keystore file on startup
+    Keystore { path: String, password: String },
+    /// key held by a signer process listening on a local unix socket
+    Remote {
+        socket_path: String,
+        key_name: String,
+    },
+}
+
+/// Creates the signer of a signing key
+pub(crate) async fn create_signer(config: SignerConfig) -> Result<Arc<dyn Signer>> {
+    match config {
+        SignerConfig::Mnemonic { mnemonic, password } => {
+            let (pair, _) = Pair::from_phrase(&mnemonic, password.as_deref())
+                .map_err(|e| anyhow!("invalid key mnemonic: {:?}", e))?;
+            Ok(Arc::new(LocalSigner::new(pair)))
+        }
+        SignerConfig::Keystore { path, password } => {
+            let keystore = Keystore::load(Path::new(&path))?;
+            if keystore.is_legacy() {
+                warn!(
+                    "keystore file {} uses the legacy cipher - migrate it with keystore change-password",
+                    path
+                );
+            }
+            let pair = keystore.unlock(&password)?;
+            Ok(Arc::new(LocalSigner::new(pair)))
+        }
+        SignerConfig::Remote {
+            socket_path,
+            key_name,
+        } => Ok(Arc::new(
+            RemoteSigner::connect(&socket_path, &key_name).await?,
+        )),
+    }
+}
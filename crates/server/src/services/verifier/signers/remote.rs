@@ -0,0 +1,85 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::signers::Signer;
+use anyhow::{anyhow, Result};
+use base::karma_coin::karma_coin_signer::signer_service_client::SignerServiceClient;
+use base::karma_coin::karma_coin_signer::{GetPublicKeyRequest, SignRequest};
+use sp_core::ed25519::{Pair, Public, Signature};
+use sp_core::Pair as PairT;
+use tokio::net::UnixStream;
+use tonic::transport::{Channel, Endpoint, Uri};
+use tower::service_fn;
+
+/// Signs with a key held by a signer process which serves the SignerService api on a local unix socket
+pub(crate) struct RemoteSigner {
+    client: SignerServiceClient<Channel>,
+    key_name: String,
+    public_key: Public,
+}
+
+impl RemoteSigner {
+    /// Connects to the signer on a unix socket and gets the public key of a key
+    pub(crate) async fn connect(socket_path: &str, key_name: &str) -> Result<Self> {
+        let path = socket_path.to_string();
+        // the endpoint uri is required by tonic but isn't used - connections are made to the socket
+        let channel = Endpoint::try_from("http://[::]:50051")?
+            .connect_with_connector(service_fn(move |_: Uri| UnixStream::connect(path.clone())))
+            .await
+            .map_err(|e| anyhow!("failed to connect to signer on {}: {}", socket_path, e))?;
+
+        let mut client = SignerServiceClient::new(channel);
+        let resp = client
+            .get_public_key(GetPublicKeyRequest {
+                key_name: key_name.into(),
+            })
+            .await
+            .map_err(|e| anyhow!("failed to get public key from signer: {}", e))?
+            .into_inner();
+
+        let public_key = Public::try_from(resp.public_key.as_slice())
+            .map_err(|_| anyhow!("signer returned an invalid public key"))?;
+
+        info!(
+            "connected to signer on {}. key: {}, public key: {}",
+            socket_path, key_name, public_key
+        );
+
+        Ok(RemoteSigner {
+            client,
+            key_name: key_name.into(),
+            public_key,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl Signer for RemoteSigner {
+    fn public_key(&self) -> Public {
+        self.public_key
+    }
+
+    async fn sign(&self, data: &[u8]) -> Result<Signature> {
+        let resp = self
+            .client
+            .clone()
+            .sign(SignRequest {
+                key_name: self.key_name.clone(),
+                data: data.to_vec(),
+            })
+            .await
+            .map_err(|e| anyhow!("signer failed to sign: {}", e))?
+            .into_inner();
+
+        let signature = Signature::from_slice(&resp.signature)
+            .ok_or_else(|| anyhow!("signer returned an invalid signature"))?;
+
+        // don't issue evidence with a signature of another key
+        if !Pair::verify(&signature, data, &self.public_key) {
+            return Err(anyhow!("signer returned a signature of another key"));
+        }
+
+        Ok(signature)
+    }
+}
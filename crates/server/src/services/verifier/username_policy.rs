@@ -0,0 +1,199 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! User names are signed into verification evidence so they are validated against a configurable policy.
+//! Names are NFKC normalized and reserved words are matched on a confusable folded skeleton of the name,
+//! so e.g. "Adm1n" and "аdmin" (cyrillic а) match the reserved word "admin".
+
+use anyhow::{anyhow, Result};
+use base::server_config_service::{
+    ServerConfigService, USERNAME_ALLOWED_CHARACTERS_CONFIG_KEY, USERNAME_MAX_LENGTH_CONFIG_KEY,
+    USERNAME_MIN_LENGTH_CONFIG_KEY, USERNAME_RESERVED_CONFIG_KEY,
+};
+use std::fmt;
+use unicode_normalization::UnicodeNormalization;
+
+/// Classes of characters user names may contain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum CharacterClass {
+    /// letters of any script
+    Letters,
+    AsciiLetters,
+    Digits,
+    Underscore,
+    Hyphen,
+    Period,
+}
+
+impl CharacterClass {
+    /// Returns the class with the provided config name
+    fn from_name(name: &str) -> Result<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "letters" => Ok(CharacterClass::Letters),
+            "ascii_letters" => Ok(CharacterClass::AsciiLetters),
+            "digits" => Ok(CharacterClass::Digits),
+            "underscore" => Ok(CharacterClass::Underscore),
+            "hyphen" => Ok(CharacterClass::Hyphen),
+            "period" => Ok(CharacterClass::Period),
+            _ => Err(anyhow!("unknown user name character class: {}", name)),
+        }
+    }
+
+    fn contains(&self, c: char) -> bool {
+        match self {
+            CharacterClass::Letters => c.is_alphabetic(),
+            CharacterClass::AsciiLetters => c.is_ascii_alphabetic(),
+            CharacterClass::Digits => c.is_ascii_digit(),
+            CharacterClass::Underscore => c == '_',
+            CharacterClass::Hyphen => c == '-',
+            CharacterClass::Period => c == '.',
+        }
+    }
+}
+
+/// Reasons a user name is rejected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum UsernameError {
+    TooShort,
+    TooLong,
+    InvalidCharacter(char),
+    Reserved,
+}
+
+impl fmt::Display for UsernameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UsernameError::TooShort => write!(f, "user name is too short"),
+            UsernameError::TooLong => write!(f, "user name is too long"),
+            UsernameError::InvalidCharacter(c) => {
+                write!(f, "user name contains invalid character: {:?}", c)
+            }
+            UsernameError::Reserved => write!(f, "user name is reserved"),
+        }
+    }
+}
+
+/// The verifier's user name policy
+#[derive(Default)]
+pub(crate) struct UsernamePolicy {
+    /// minimum length in characters
+    min_length: usize,
+    /// maximum length in characters
+    max_length: usize,
+    allowed_characters: Vec<CharacterClass>,
+    /// skeletons of the reserved words
+    reserved: Vec<String>,
+}
+
+impl UsernamePolicy {
+    /// Loads the policy from the server config
+    pub(crate) async fn from_config() -> Result<Self> {
+        let min_length = ServerConfigService::get_u64(USERNAME_MIN_LENGTH_CONFIG_KEY.into())
+            .await?
+            .unwrap() as usize;
+
+        let max_length = ServerConfigService::get_u64(USERNAME_MAX_LENGTH_CONFIG_KEY.into())
+            .await?
+            .unwrap() as usize;
+
+        if min_length == 0 || min_length > max_length {
+            return Err(anyhow!(
+                "invalid user name length limits: {} - {}",
+                min_length,
+                max_length
+            ));
+        }
+
+        let allowed_characters = ServerConfigService::get_typed::<Vec<String>>(
+            USERNAME_ALLOWED_CHARACTERS_CONFIG_KEY.into(),
+        )
+        .await?
+        .unwrap()
+        .iter()
+        .map(|name| CharacterClass::from_name(name))
+        .collect::<Result<Vec<CharacterClass>>>()?;
+
+        let reserved =
+            ServerConfigService::get_typed::<Vec<String>>(USERNAME_RESERVED_CONFIG_KEY.into())
+                .await?
+                .unwrap_or_default()
+                .iter()
+                .map(|word| skeleton(word))
+                .collect();
+
+        Ok(UsernamePolicy {
+            min_length,
+            max_length,
+            allowed_characters,
+            reserved,
+        })
+    }
+
+    /// Validates a user name and returns its NFKC normalized form
+    pub(crate) fn validate(&self, user_name: &str) -> Result<String, UsernameError> {
+        let normalized: String = user_name.nfkc().collect();
+
+        let length = normalized.chars().count();
+        if length < self.min_length {
+            return Err(UsernameError::TooShort);
+        }
+
+        if length > self.max_length {
+            return Err(UsernameError::TooLong);
+        }
+
+        if let Some(c) = normalized.chars().find(|c| {
+            !self
+                .allowed_characters
+                .iter()
+                .any(|class| class.contains(*c))
+        }) {
+            return Err(UsernameError::InvalidCharacter(c));
+        }
+
+        if self.reserved.contains(&skeleton(&normalized)) {
+            return Err(UsernameError::Reserved);
+        }
+
+        Ok(normalized)
+    }
+}
+
+/// Returns the form of a name used to compare it with reserved words -
+/// lower case, without separators and with confusable characters folded to a common character
+fn skeleton(name: &str) -> String {
+    name.nfkc()
+        .flat_map(char::to_lowercase)
+        .filter(|c| !matches!(c, '_' | '-' | '.' | ' '))
+        .map(fold_confusable)
+        .collect()
+}
+
+/// Folds characters commonly used to imitate latin letters
+fn fold_confusable(c: char) -> char {
+    match c {
+        '0' | 'о' | 'ο' => 'o',
+        '1' | 'l' | 'ı' | 'і' | 'ӏ' | '|' => 'i',
+        '3' | 'е' | 'ε' => 'e',
+        '4' | 'а' | 'α' => 'a',
+        '5' | 'ѕ' => 's',
+        '7' => 't',
+        '8' => 'b',
+        'с' | 'ϲ' => 'c',
+        'р' | 'ρ' => 'p',
+        'х' | 'χ' => 'x',
+        'у' | 'γ' => 'y',
+        'к' | 'κ' => 'k',
+        'м' => 'm',
+        'н' => 'h',
+        'т' | 'τ' => 't',
+        'в' => 'b',
+        'ν' => 'v',
+        'ј' => 'j',
+        'ԁ' => 'd',
+        'ɡ' => 'g',
+        'ո' => 'n',
+        _ => c,
+    }
+}
@@ -2,38 +2,85 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::verifier::bypass_tokens::BypassTokens;
+use crate::services::verifier::channels::ChannelsConfig;
+use crate::services::verifier::country_policy::CountryPolicy;
+use crate::services::verifier::get_pre_key_bundle::GetPreKeyBundle;
+use crate::services::verifier::get_verifier_info::GetVerifierInfo;
+use crate::services::verifier::key_ring::KeyRing;
+use crate::services::verifier::phone_number_hasher::PhoneNumberHasher;
+use crate::services::verifier::pre_key_service::{Decrypt, PreKeyService};
+use crate::services::verifier::providers::Providers;
+use crate::services::verifier::rate_limiter::{RateLimitExceeded, RateLimits};
+use crate::services::verifier::replay_guard::ReplayGuard;
 use crate::services::verifier::send_verification_code::SendVerificationCode;
+use crate::services::verifier::sessions::SessionStore;
+use crate::services::verifier::username_policy::UsernamePolicy;
+use crate::services::verifier::verify_evidence::VerifyEvidence;
 use crate::services::verifier::verify_number::Verify;
-use anyhow::Result;
-use base::hex_utils::hex_string;
+use anyhow::{anyhow, Result};
 use base::karma_coin::karma_coin_verifier::verifier_service_server::VerifierService as VerifierServiceTrait;
 use base::karma_coin::karma_coin_verifier::{
-    SendVerificationCodeRequest, SendVerificationCodeResponse, SendVerificationCodeResult,
-    VerifyNumberRequest, VerifyNumberResponse,
+    EncryptedPayload, GetPreKeyBundleRequest, GetPreKeyBundleResponse, GetVerifierInfoRequest,
+    GetVerifierInfoResponse, SendVerificationCodeRequest, SendVerificationCodeResponse,
+    SendVerificationCodeResult, VerifyEvidenceRequest, VerifyEvidenceResponse, VerifyNumberRequest,
+    VerifyNumberResponse,
 };
-use base::server_config_service::ServerConfigService;
-use sp_core::ed25519::Pair as ED25519;
-use sp_core::*;
+use base::server_config_service::{
+    ServerConfigService, VERIFIER_EVIDENCE_TTL_SECS_CONFIG_KEY, VERIFIER_NAME,
+    VERIFIER_SS58_PREFIX_CONFIG_KEY,
+};
+use chrono::Utc;
 use tonic::{Request, Response, Status};
 use xactor::*;
 
 /// ApiService is a system service that provides access to provider server persisted data as well as an interface to admin the provider's server. It provides a GRPC admin service defined in ServerAdminService. This service is designed to be used by provider admin clients.
 pub(crate) struct VerifierService {
-    pub(crate) twilio_account_id: Option<String>,
-    pub(crate) twilio_service_id: Option<String>,
-    pub(crate) twilio_token: Option<String>,
-    /// verifier key pair - generated on startup
-    pub(crate) key_pair: Option<ED25519>,
+    /// verifier display name
+    pub(crate) name: String,
+    /// verification providers used to send and check codes - created on startup
+    pub(crate) providers: Providers,
+    /// code delivery channels users may request
+    pub(crate) channels: ChannelsConfig,
+    /// countries numbers may be verified in
+    pub(crate) countries: CountryPolicy,
+    /// verification sessions created by SendVerificationCode
+    pub(crate) sessions: SessionStore,
+    /// rejects replayed verify number requests
+    pub(crate) replay_guard: ReplayGuard,
+    /// api rate limits by phone number, account and client ip
+    pub(crate) rate_limits: RateLimits,
+    /// tokens which verify numbers without a code
+    pub(crate) bypass_tokens: BypassTokens,
+    /// user names which may be verified
+    pub(crate) username_policy: UsernamePolicy,
+    /// time issued evidence is valid for in milliseconds
+    pub(crate) evidence_ttl: u64,
+    /// hashes phone numbers of issued evidence
+    pub(crate) phone_number_hasher: PhoneNumberHasher,
+    /// network prefix account ids must have - any prefix is accepted when not set
+    pub(crate) ss58_prefix: Option<u16>,
+    /// verifier signing keys - loaded on startup and reloaded by ReloadKeys
+    pub(crate) key_ring: KeyRing,
 }
 
 impl Default for VerifierService {
     fn default() -> Self {
         info!("Verifier Service created");
         VerifierService {
-            twilio_account_id: None,
-            twilio_service_id: None,
-            twilio_token: None,
-            key_pair: None,
+            name: String::new(),
+            providers: Providers::default(),
+            channels: ChannelsConfig::default(),
+            countries: CountryPolicy::default(),
+            sessions: SessionStore::default(),
+            replay_guard: ReplayGuard::default(),
+            rate_limits: RateLimits::default(),
+            bypass_tokens: BypassTokens::default(),
+            username_policy: UsernamePolicy::default(),
+            evidence_ttl: 0,
+            phone_number_hasher: PhoneNumberHasher::default(),
+            ss58_prefix: None,
+            key_ring: KeyRing::default(),
         }
     }
 }
@@ -41,44 +88,48 @@ impl Default for VerifierService {
 #[async_trait::async_trait]
 impl Actor for VerifierService {
     async fn started(&mut self, _ctx: &mut Context<Self>) -> Result<()> {
-        // generate verifier identity from config secrets
-        let phrase = ServerConfigService::get("verifier.key_mnemonic".into())
+        self.name = ServerConfigService::get(VERIFIER_NAME.into())
             .await?
-            .unwrap();
+            .unwrap_or_default();
 
-        let password = ServerConfigService::get("verifier.key_password".into())
-            .await?
-            .unwrap();
+        self.key_ring = KeyRing::from_config().await?;
 
-        let (pair, _) = ED25519::from_phrase(&phrase, Some(&password)).unwrap();
+        self.channels = ChannelsConfig::from_config().await?;
 
-        info!(
-            "Verifier identity public key raw: {}",
-            hex_string(&pair.public().to_raw_vec())
-        );
+        self.countries = CountryPolicy::from_config().await?;
 
-        info!("Verifier identity public key ss58: {}", pair.public());
+        self.providers = Providers::from_config(&self.channels).await?;
 
-        // Store identity for signing messages
-        self.key_pair = Some(pair);
+        self.sessions = SessionStore::from_config().await?;
 
-        self.twilio_account_id = Some(
-            ServerConfigService::get("twilio.account_sid".into())
-                .await?
-                .unwrap(),
-        );
+        self.replay_guard = ReplayGuard::from_config().await?;
 
-        self.twilio_service_id = Some(
-            ServerConfigService::get("twilio.service_id".into())
-                .await?
-                .unwrap(),
-        );
+        self.rate_limits = RateLimits::from_config().await?;
 
-        self.twilio_token = Some(
-            ServerConfigService::get("twilio.auth_token".into())
+        self.bypass_tokens = BypassTokens::from_config().await?;
+
+        self.username_policy = UsernamePolicy::from_config().await?;
+
+        let evidence_ttl_secs =
+            ServerConfigService::get_u64(VERIFIER_EVIDENCE_TTL_SECS_CONFIG_KEY.into())
                 .await?
-                .unwrap(),
-        );
+                .unwrap();
+        // the expiry time of evidence issued now must fit in a u64
+        self.evidence_ttl = evidence_ttl_secs
+            .checked_mul(1000)
+            .filter(|ttl| {
+                (Utc::now().timestamp_millis() as u64)
+                    .checked_add(*ttl)
+                    .is_some()
+            })
+            .ok_or_else(|| anyhow!("evidence ttl is too long: {}", evidence_ttl_secs))?;
+
+        self.phone_number_hasher = PhoneNumberHasher::from_config().await?;
+
+        self.ss58_prefix = ServerConfigService::get_u64(VERIFIER_SS58_PREFIX_CONFIG_KEY.into())
+            .await?
+            .map(u16::try_from)
+            .transpose()?;
 
         info!("Verifier service initialized and started");
 
@@ -88,6 +139,16 @@ impl Actor for VerifierService {
 
 impl Service for VerifierService {}
 
+impl VerifierService {
+    /// Decrypts a request payload encrypted to the verifier's pre-key bundle
+    pub(crate) async fn decrypt_payload(&self, payload: EncryptedPayload) -> Result<Vec<u8>> {
+        PreKeyService::from_registry()
+            .await?
+            .call(Decrypt(payload))
+            .await?
+    }
+}
+
 #[tonic::async_trait]
 impl VerifierServiceTrait for VerifierService {
     async fn send_verification_code(
@@ -98,8 +159,12 @@ impl VerifierServiceTrait for VerifierService {
             .await
             .map_err(|e| Status::internal(format!("internal error: {:?}", e)))?;
 
+        let client_ip = request.remote_addr().map(|addr| addr.ip());
         match service
-            .call(SendVerificationCode(request.into_inner()))
+            .call(SendVerificationCode {
+                request: request.into_inner(),
+                client_ip,
+            })
             .await
             .map_err(|e| Status::internal(format!("failed to call verifier api: {:?}", e)))?
         {
@@ -110,7 +175,7 @@ impl VerifierServiceTrait for VerifierService {
                 }
                 Ok(Response::new(resp))
             }
-            Err(e) => Err(Status::internal(format!("internal error: {:?}", e))),
+            Err(e) => Err(error_status(e)),
         }
     }
 
@@ -123,8 +188,12 @@ impl VerifierServiceTrait for VerifierService {
             .await
             .map_err(|e| Status::internal(format!("internal error: {:?}", e)))?;
 
+        let client_ip = request.remote_addr().map(|addr| addr.ip());
         match service
-            .call(Verify(request.into_inner()))
+            .call(Verify {
+                request: request.into_inner(),
+                client_ip,
+            })
             .await
             .map_err(|e| Status::internal(format!("failed to call verifier api: {:?}", e)))?
         {
@@ -132,7 +201,109 @@ impl VerifierServiceTrait for VerifierService {
                 info!("verification successful");
                 Ok(Response::new(resp))
             }
-            Err(e) => Err(Status::internal(format!("internal error: {:?}", e))),
+            Err(e) => Err(error_status(e)),
+        }
+    }
+
+    /// Check evidence issued by this verifier
+    async fn verify_evidence(
+        &self,
+        request: Request<VerifyEvidenceRequest>,
+    ) -> Result<Response<VerifyEvidenceResponse>, Status> {
+        let service = VerifierService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {:?}", e)))?;
+
+        let client_ip = request.remote_addr().map(|addr| addr.ip());
+        match service
+            .call(VerifyEvidence {
+                request: request.into_inner(),
+                client_ip,
+            })
+            .await
+            .map_err(|e| Status::internal(format!("failed to call verifier api: {:?}", e)))?
+        {
+            Ok(resp) => {
+                info!("evidence status: {:?}", resp.status);
+                Ok(Response::new(resp))
+            }
+            Err(e) => Err(error_status(e)),
         }
     }
+
+    /// Get the verifier's identity, keys and capabilities
+    async fn get_verifier_info(
+        &self,
+        request: Request<GetVerifierInfoRequest>,
+    ) -> Result<Response<GetVerifierInfoResponse>, Status> {
+        let service = VerifierService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {:?}", e)))?;
+
+        let client_ip = request.remote_addr().map(|addr| addr.ip());
+        match service
+            .call(GetVerifierInfo { client_ip })
+            .await
+            .map_err(|e| Status::internal(format!("failed to call verifier api: {:?}", e)))?
+        {
+            Ok(verifier_info) => Ok(Response::new(GetVerifierInfoResponse {
+                verifier_info: Some(verifier_info),
+            })),
+            Err(e) => Err(error_status(e)),
+        }
+    }
+
+    async fn get_pre_key_bundle(
+        &self,
+        request: Request<GetPreKeyBundleRequest>,
+    ) -> Result<Response<GetPreKeyBundleResponse>, Status> {
+        let service = VerifierService::from_registry()
+            .await
+            .map_err(|e| Status::internal(format!("internal error: {:?}", e)))?;
+
+        let client_ip = request.remote_addr().map(|addr| addr.ip());
+        match service
+            .call(GetPreKeyBundle { client_ip })
+            .await
+            .map_err(|e| Status::internal(format!("failed to call verifier api: {:?}", e)))?
+        {
+            Ok(pre_key_bundle) => Ok(Response::new(GetPreKeyBundleResponse {
+                pre_key_bundle: Some(pre_key_bundle),
+            })),
+            Err(e) => Err(error_status(e)),
+        }
+    }
+}
+
+/// Maps an api handler error to a grpc status
+fn error_status(e: anyhow::Error) -> Status {
+    match e.downcast_ref::<RateLimitExceeded>() {
+        Some(rate_limit) => {
+            info!("{}", rate_limit);
+            let mut status = Status::resource_exhausted(rate_limit.to_string());
+            // retry-after is in whole seconds, rounded up
+            let retry_after = rate_limit.retry_after;
+            let retry_after_secs =
+                retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            status
+                .metadata_mut()
+                .insert("retry-after", retry_after_secs.into());
+            status
+        }
+        None => Status::internal(format!("internal error: {:?}", e)),
+    }
+}
+
+#[message(result = "Result<()>")]
+pub(crate) struct ReloadKeys;
+
+/// Reloads the verifier signing keys from the server config so keys can be rotated without a restart.
+/// The current keys are kept when the configured keys are invalid.
+#[async_trait::async_trait]
+impl Handler<ReloadKeys> for VerifierService {
+    async fn handle(&mut self, _ctx: &mut Context<Self>, _msg: ReloadKeys) -> Result<()> {
+        self.key_ring = KeyRing::from_config().await?;
+        info!("verifier keys reloaded");
+        Ok(())
+    }
 }
@@ -0,0 +1,107 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+use crate::services::verifier::evidence::VerificationEvidenceEnvelope;
+use crate::services::verifier::verifier_service::VerifierService;
+use anyhow::Result;
+use base::karma_coin::karma_coin_verifier::{
+    EvidenceStatus, PhoneNumberHashScheme, VerifyEvidenceRequest, VerifyEvidenceResponse,
+};
+use chrono::Utc;
+use parity_scale_codec::DecodeAll;
+use sp_core::crypto::{Ss58AddressFormat, Ss58Codec};
+use sp_core::ed25519::{Pair, Signature};
+use sp_core::Pair as PairT;
+use std::net::IpAddr;
+use xactor::*;
+
+#[message(result = "Result<VerifyEvidenceResponse>")]
+pub(crate) struct VerifyEvidence {
+    pub(crate) request: VerifyEvidenceRequest,
+    /// address of the calling client - used for rate limiting
+    pub(crate) client_ip: Option<IpAddr>,
+}
+
+/// Request to check evidence previously issued by this verifier
+#[async_trait::async_trait]
+impl Handler<VerifyEvidence> for VerifierService {
+    async fn handle(
+        &mut self,
+        _ctx: &mut Context<Self>,
+        msg: VerifyEvidence,
+    ) -> Result<VerifyEvidenceResponse> {
+        self.rate_limits.check_client_ip(msg.client_ip)?;
+
+        let req = msg.request;
+
+        let evidence = match VerificationEvidenceEnvelope::decode_all(&mut req.evidence.as_slice())
+        {
+            Ok(evidence) => evidence,
+            Err(e) => {
+                info!("failed to decode evidence: {}", e);
+                return Ok(gen_evidence_status(EvidenceStatus::Malformed));
+            }
+        };
+
+        // only evidence signed by the current or a past key of this verifier is checked
+        let now = Utc::now().timestamp_millis() as u64;
+        if !self
+            .key_ring
+            .is_verification_key(&evidence.verifier_public_key, now)
+        {
+            info!(
+                "evidence verifier key {} is not a key of this verifier",
+                evidence.verifier_public_key
+            );
+            return Ok(gen_evidence_status(EvidenceStatus::InvalidSignature));
+        }
+        let signed_by_current_key = self
+            .key_ring
+            .signing_key(now)
+            .map_or(false, |key| key.public_key == evidence.verifier_public_key);
+
+        let signature = match Signature::from_slice(req.signature.as_ref()) {
+            Some(signature) => signature,
+            None => return Ok(gen_evidence_status(EvidenceStatus::InvalidSignature)),
+        };
+
+        if !Pair::verify(&signature, &req.evidence, &evidence.verifier_public_key) {
+            return Ok(gen_evidence_status(EvidenceStatus::InvalidSignature));
+        }
+
+        let status = if now >= evidence.expires_at {
+            EvidenceStatus::Expired
+        } else {
+            EvidenceStatus::Valid
+        };
+
+        let account_id = match self.ss58_prefix {
+            Some(prefix) => evidence
+                .account_id
+                .to_ss58check_with_version(Ss58AddressFormat::custom(prefix)),
+            None => evidence.account_id.to_ss58check(),
+        };
+
+        Ok(VerifyEvidenceResponse {
+            status: status as i32,
+            signed_by_current_key,
+            verifier_public_key: evidence.verifier_public_key.0.to_vec(),
+            account_id,
+            user_name: evidence.username,
+            phone_number_hash: evidence.phone_number_hash.to_vec(),
+            issued_at: evidence.issued_at,
+            expires_at: evidence.expires_at,
+            phone_number_hash_scheme: PhoneNumberHashScheme::from(evidence.phone_number_hash_scheme)
+                as i32,
+        })
+    }
+}
+
+/// private helper function to generate a response for evidence which isn't checked
+fn gen_evidence_status(status: EvidenceStatus) -> VerifyEvidenceResponse {
+    VerifyEvidenceResponse {
+        status: status as i32,
+        ..Default::default()
+    }
+}
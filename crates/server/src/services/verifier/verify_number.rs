@@ -2,32 +2,29 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
+use crate::services::verifier::evidence::VerificationEvidenceEnvelope;
+use crate::services::verifier::providers::{ProviderError, VerificationProvider, APPROVED_STATUS};
+use crate::services::verifier::replay_guard::ReplayError;
+use crate::services::verifier::request_validation::validate_request;
+use crate::services::verifier::sessions::SessionError;
 use crate::services::verifier::verifier_service::VerifierService;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use base::karma_coin::karma_coin_verifier::{
-    VerificationResult, VerifyNumberRequest, VerifyNumberRequestData, VerifyNumberResponse,
+    UserVerificationData, VerificationResult, VerifyNumberRequest, VerifyNumberResponse,
 };
-use base::server_config_service::ServerConfigService;
-use http::{header, StatusCode};
-use prost::Message;
-use reqwest::Client;
-use serde::Deserialize;
-use sp_core::{
-    crypto::{AccountId32, Ss58Codec},
-    ed25519::{Pair, Public, Signature},
-    Encode, Pair as PairT,
-};
-use std::collections::HashMap;
+use chrono::Utc;
+use parity_scale_codec::Encode;
+use std::net::IpAddr;
+use std::sync::Arc;
 use xactor::*;
 
 #[message(result = "Result<VerifyNumberResponse>")]
-pub(crate) struct Verify(pub VerifyNumberRequest);
-
-#[derive(Deserialize, Debug, Clone)]
-pub struct OTPVerifyResponse {
-    pub status: String,
-    pub sid: String,
+pub(crate) struct Verify {
+    pub(crate) request: VerifyNumberRequest,
+    /// address of the calling client - used for rate limiting
+    pub(crate) client_ip: Option<IpAddr>,
 }
+
 /// Request to complete verification and sign up
 #[async_trait::async_trait]
 impl Handler<Verify> for VerifierService {
@@ -36,125 +33,194 @@ impl Handler<Verify> for VerifierService {
         _ctx: &mut Context<Self>,
         msg: Verify,
     ) -> Result<VerifyNumberResponse> {
-        let req = msg.0;
+        let mut req = msg.request;
+
+        self.rate_limits.check_client_ip(msg.client_ip)?;
 
         info!("verify phone number called");
 
-        // decode request data
-        let user_data = match VerifyNumberRequestData::decode(req.data.as_ref()) {
-            Ok(user_data) => user_data,
-            Err(_) => {
-                return gen_verification_result(VerificationResult::MissingData).await;
+        // use the data encrypted to the verifier's pre-key bundle when provided
+        if let Some(payload) = req.encrypted_data.take() {
+            if !req.data.is_empty() {
+                info!("request has both plaintext and encrypted data");
+                return gen_verification_result(VerificationResult::InvalidEncryption).await;
             }
-        };
 
-        if user_data.account_id.is_empty() {
-            return gen_verification_result(VerificationResult::MissingData).await;
+            req.data = match self.decrypt_payload(payload).await {
+                Ok(data) => data,
+                Err(e) => {
+                    info!("failed to decrypt request data: {}", e);
+                    return gen_verification_result(VerificationResult::InvalidEncryption).await;
+                }
+            };
         }
 
-        if user_data.user_name.is_empty() {
-            return gen_verification_result(VerificationResult::MissingData).await;
-        }
+        let request = match validate_request(&req, self.ss58_prefix) {
+            Ok(request) => request,
+            Err(e) => {
+                info!("invalid verify number request: {}", e);
+                return gen_verification_result(e.result()).await;
+            }
+        };
+        let user_data = request.data;
+        let account_id = request.account_id;
 
-        if user_data.phone_number.is_empty() {
-            return gen_verification_result(VerificationResult::MissingData).await;
+        if !self.countries.is_allowed(request.phone_number.country_code) {
+            info!(
+                "country code {} is not allowed",
+                request.phone_number.country_code
+            );
+            return gen_verification_result(VerificationResult::RegionNotSupported).await;
         }
+        let phone_number = request.phone_number.number;
 
-        let signature = Signature::from_slice(req.signature.as_ref()).unwrap();
-        let account_id = AccountId32::from_ss58check(&user_data.account_id).unwrap();
-        let pub_key = Public::from_raw(*account_id.as_ref());
+        let user_name = match self.username_policy.validate(&user_data.user_name) {
+            Ok(user_name) => user_name,
+            Err(e) => {
+                info!("invalid user name {}: {}", user_data.user_name, e);
+                return gen_verification_result(VerificationResult::InvalidUserName).await;
+            }
+        };
+
+        // keyed by the decoded account so an account can't get more buckets by changing its ss58 prefix
+        self.rate_limits.account.check(account_id.clone())?;
 
-        // verify request data signature
-        if !Pair::verify(&signature, req.data, &pub_key) {
-            return gen_verification_result(VerificationResult::InvalidSignature).await;
+        // resolve the signing key before any token, code or session is consumed
+        let now = Utc::now().timestamp_millis() as u64;
+        let (signer, verifier_public_key) = match self.key_ring.signing_key(now) {
+            Some(key) => (key.signer.clone(), key.public_key),
+            None => return Err(anyhow!("no active verifier signing key")),
         };
 
-        let bypass_token = ServerConfigService::get("verifier.bypass_token".into())
-            .await?
-            .unwrap();
+        // reject stale and replayed requests
+        match self.replay_guard.check(&req.data, user_data.timestamp) {
+            Ok(()) => {}
+            Err(ReplayError::Stale) => {
+                info!(
+                    "rejecting stale request. timestamp: {}",
+                    user_data.timestamp
+                );
+                return gen_verification_result(VerificationResult::StaleRequest).await;
+            }
+            Err(ReplayError::Duplicate) => {
+                info!("rejecting duplicate request");
+                return gen_verification_result(VerificationResult::DuplicateRequest).await;
+            }
+        }
 
-        // call auth service unless bypass token was provided and matches the configured one
-        if !user_data.bypass_token.eq(&bypass_token) {
-            // verify code
+        // use a provided bypass token or check the code with the provider
+        let bypassed = !user_data.bypass_token.is_empty()
+            && match self.bypass_tokens.use_token(
+                &user_data.bypass_token,
+                &phone_number,
+                &user_data.account_id,
+            ) {
+                Ok(name) => {
+                    info!("verification bypassed with token {}", name);
+                    true
+                }
+                Err(e) => {
+                    info!("bypass token not accepted: {}", e);
+                    false
+                }
+            };
 
-            let url = format!(
-                "https://verify.twilio.com/v2/Services/{serv_id}/VerificationCheck",
-                serv_id = self.twilio_service_id.as_ref().unwrap(),
-            );
+        if !bypassed {
+            // verify code
+            // only check codes of sessions issued to this number
+            let session = match self
+                .sessions
+                .get_valid(&user_data.verification_sid, &phone_number)
+            {
+                Ok(session) => session,
+                Err(SessionError::Locked) => {
+                    info!("rejecting locked session {}", user_data.verification_sid);
+                    return gen_verification_result(VerificationResult::TooManyAttempts).await;
+                }
+                Err(e) => {
+                    info!("rejecting session {}: {}", user_data.verification_sid, e);
+                    return gen_verification_result(VerificationResult::InvalidSession).await;
+                }
+            };
 
-            let mut headers = header::HeaderMap::new();
-            headers.insert(
-                "Content-Type",
-                "application/x-www-form-urlencoded".parse().unwrap(),
+            info!(
+                "checking code of session {}. failed attempts: {}",
+                session.sid, session.failed_attempts
             );
 
-            let mut form_body: HashMap<&str, &String> = HashMap::new();
-            form_body.insert("To", &user_data.phone_number);
-            form_body.insert("Code", &user_data.verification_code);
-
-            let client = Client::new();
-            let res = client
-                .post(url)
-                .basic_auth(
-                    self.twilio_account_id.as_ref().unwrap(),
-                    Some(self.twilio_token.as_ref().unwrap()),
-                )
-                .headers(headers)
-                .form(&form_body)
-                .send()
-                .await;
-
-            match res {
-                Ok(response) => {
-                    if response.status() != StatusCode::OK {
-                        info!("twilio response status code != 200");
-                        return gen_verification_result(VerificationResult::Failed).await;
-                    }
+            // check the code with the provider which issued the session.
+            // the provider may have been removed from the config since the session was issued
+            let provider = match self.providers.get(session.provider_name) {
+                Some(provider) => provider,
+                None => {
+                    info!(
+                        "provider {} of session {} is not configured",
+                        session.provider_name, user_data.verification_sid
+                    );
+                    return gen_verification_result(VerificationResult::InvalidSession).await;
+                }
+            };
 
-                    let data = response.json::<OTPVerifyResponse>().await;
-                    match data {
-                        Ok(result) => {
-                            if result.status == "approved" {
-                                // validate sid
-                                if result.sid != user_data.verification_sid {
-                                    info!("twilio sid mismatch");
-                                    return gen_verification_result(
-                                        VerificationResult::MissingData,
-                                    )
-                                    .await;
-                                }
-                                info!("Twilio approved code!");
-                            } else {
-                                info!("Twilio result != approved: {}", result.status);
-                                return gen_verification_result(VerificationResult::Failed).await;
-                            }
-                        }
-                        Err(e) => {
-                            info!("error parsing twilio resp: {}", e);
-                            return gen_verification_result(VerificationResult::Failed).await;
+            match provider
+                .check_code(&phone_number, &user_data.verification_code)
+                .await
+            {
+                Ok(result) => {
+                    if result.status == APPROVED_STATUS {
+                        // validate sid
+                        if result.sid != user_data.verification_sid {
+                            info!("provider sid mismatch");
+                            return gen_verification_result(VerificationResult::MissingData).await;
                         }
+                        info!("{} approved code!", provider.name());
+                        self.sessions.remove(&result.sid);
+                    } else {
+                        info!("provider result != approved: {}", result.status);
+                        self.record_failed_attempt(&provider, &user_data.verification_sid)
+                            .await;
+                        return gen_verification_result(VerificationResult::Failed).await;
                     }
                 }
+                Err(ProviderError::Rejected(status)) => {
+                    info!(
+                        "{} rejected code check. status code: {}",
+                        provider.name(),
+                        status
+                    );
+                    self.record_failed_attempt(&provider, &user_data.verification_sid)
+                        .await;
+                    return gen_verification_result(VerificationResult::Failed).await;
+                }
                 Err(e) => {
-                    info!("error calling twilio: {}", e);
+                    // provider failures are not counted as failed attempts
+                    info!("error checking code with {}: {}", provider.name(), e);
                     return gen_verification_result(VerificationResult::Failed).await;
                 }
             }
         }
 
-        let phone_number_hash =
-            sp_core::hashing::blake2_512(user_data.phone_number.clone().as_bytes());
-        let verification_evidence = sp_rpc::verifier::VerificationEvidence {
-            verifier_public_key: self.key_pair.unwrap().public(),
+        let evidence = VerificationEvidenceEnvelope::new(
+            verifier_public_key,
             account_id,
-            username: user_data.user_name,
-            phone_number_hash,
-        };
-        let bytes = verification_evidence.encode();
+            user_name,
+            self.phone_number_hasher.hash(&phone_number)?,
+            now,
+            self.evidence_ttl,
+        )?;
+
+        let evidence_bytes = evidence.encode();
+        let signature = signer.sign(&evidence_bytes).await?.0.to_vec();
 
         let response = VerifyNumberResponse {
-            data: self.key_pair.unwrap().sign(&bytes).0.to_vec(),
+            data: signature.clone(),
             result: VerificationResult::Verified as i32,
+            issued_at: evidence.issued_at,
+            expires_at: evidence.expires_at,
+            user_verification_data: Some(UserVerificationData {
+                evidence: evidence_bytes,
+                verifier_public_key: verifier_public_key.0.to_vec(),
+                signature,
+            }),
         };
 
         info!("Returning verification response");
@@ -162,10 +228,32 @@ impl Handler<Verify> for VerifierService {
     }
 }
 
+impl VerifierService {
+    /// Counts a wrong code checked in a session and cancels the provider's session once it is locked
+    async fn record_failed_attempt(&mut self, provider: &Arc<dyn VerificationProvider>, sid: &str) {
+        if !self.sessions.record_failed_attempt(sid) {
+            return;
+        }
+
+        info!("session {} locked after too many failed attempts", sid);
+        if let Err(e) = provider.cancel_session(sid).await {
+            warn!(
+                "failed to cancel session {} with {}: {}",
+                sid,
+                provider.name(),
+                e
+            );
+        }
+    }
+}
+
 /// private helper function to generate a failure result
 async fn gen_verification_result(result: VerificationResult) -> Result<VerifyNumberResponse> {
     Ok(VerifyNumberResponse {
         data: vec![],
         result: result as i32,
+        issued_at: 0,
+        expires_at: 0,
+        user_verification_data: None,
     })
 }
@@ -0,0 +1,106 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of verifying numbers with named bypass tokens
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::VerificationResult;
+use common::{run_test, TestEnv, TestUser, BYPASS_TOKEN, MOBILE_NUMBER, NO_RATE_LIMITS};
+use std::time::Duration;
+
+async fn env() -> &'static TestEnv {
+    TestEnv::get(&format!(
+        r#"  bypass_tokens:
+    - name: scoped
+      token: scoped-token
+      phone_number_prefix: "+1415555"
+    - name: quota
+      token: quota-token
+      max_uses: 2
+    - name: expired
+      token: expired-token
+      expires_at: 1000
+{}"#,
+        NO_RATE_LIMITS
+    ))
+    .await
+}
+
+/// Verifies MOBILE_NUMBER or another number with a bypass token
+async fn verify_with_token(env: &TestEnv, phone_number: &str, token: &str) -> i32 {
+    let user = TestUser::generate();
+    let mut data = user.request_data(phone_number, "", "");
+    data.bypass_token = token.into();
+    env.verify_number(user.sign_request(&data)).await.result
+}
+
+#[test]
+fn scoped_token() {
+    run_test(async {
+        let env = env().await;
+        assert_eq!(
+            verify_with_token(env, MOBILE_NUMBER, "scoped-token").await,
+            VerificationResult::Verified as i32
+        );
+
+        // numbers outside of the token's scope are checked with the provider
+        assert_eq!(
+            verify_with_token(env, "+442071838750", "scoped-token").await,
+            VerificationResult::InvalidSession as i32
+        );
+    })
+}
+
+#[test]
+fn token_quota() {
+    run_test(async {
+        let env = env().await;
+        for _ in 0..2 {
+            assert_eq!(
+                verify_with_token(env, MOBILE_NUMBER, "quota-token").await,
+                VerificationResult::Verified as i32
+            );
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+
+        assert_eq!(
+            verify_with_token(env, MOBILE_NUMBER, "quota-token").await,
+            VerificationResult::InvalidSession as i32
+        );
+    })
+}
+
+#[test]
+fn expired_token() {
+    run_test(async {
+        let env = env().await;
+        assert_eq!(
+            verify_with_token(env, MOBILE_NUMBER, "expired-token").await,
+            VerificationResult::InvalidSession as i32
+        );
+    })
+}
+
+#[test]
+fn unknown_token() {
+    run_test(async {
+        let env = env().await;
+        assert_eq!(
+            verify_with_token(env, MOBILE_NUMBER, "scoped-token-2").await,
+            VerificationResult::InvalidSession as i32
+        );
+    })
+}
+
+#[test]
+fn legacy_token() {
+    run_test(async {
+        let env = env().await;
+        assert_eq!(
+            verify_with_token(env, MOBILE_NUMBER, BYPASS_TOKEN).await,
+            VerificationResult::Verified as i32
+        );
+    })
+}
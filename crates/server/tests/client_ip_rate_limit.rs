@@ -0,0 +1,89 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of the verifier's client ip rate limit.
+//! All requests of a test binary come from the same address so this limit has its own test binary.
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    GetPreKeyBundleRequest, GetVerifierInfoRequest, SendVerificationCodeRequest,
+    SendVerificationCodeResult, VerificationChannel, VerifyEvidenceRequest,
+};
+use common::{run_test, TestEnv, TestUser, BYPASS_TOKEN, MOBILE_NUMBER};
+use tonic::Code;
+
+async fn env() -> &'static TestEnv {
+    TestEnv::get(
+        r#"  rate_limits:
+    phone_number:
+      capacity: 0
+    account:
+      capacity: 0
+    client_ip:
+      capacity: 3
+      window_secs: 3600"#,
+    )
+    .await
+}
+
+#[test]
+fn client_ip_rate_limit() {
+    run_test(async {
+        let env = env().await;
+        let request = SendVerificationCodeRequest {
+            mobile_number: "".into(),
+            channel: VerificationChannel::Unspecified as i32,
+            encrypted_request: None,
+        };
+
+        for _ in 0..3 {
+            let resp = env.send_verification_code(request.clone()).await;
+            assert_eq!(
+                resp.result,
+                SendVerificationCodeResult::InvalidUserData as i32
+            );
+        }
+
+        let status = env
+            .client()
+            .send_verification_code(request)
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert!(status.metadata().get("retry-after").is_some());
+
+        // the limit applies to all apis
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let status = env
+            .client()
+            .verify_number(user.sign_request(&data))
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let status = env
+            .client()
+            .verify_evidence(VerifyEvidenceRequest::default())
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let status = env
+            .client()
+            .get_verifier_info(GetVerifierInfoRequest {})
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let status = env
+            .client()
+            .get_pre_key_bundle(GetPreKeyBundleRequest {})
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    })
+}
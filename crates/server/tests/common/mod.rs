@@ -2,143 +2,340 @@
 // This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
 //
 
-use anyhow::anyhow;
-use base::genesis_config_service::{GenesisConfigService, NET_ID_KEY};
-use base::karma_coin::karma_coin_api::api_service_client::ApiServiceClient;
-use base::karma_coin::karma_coin_api::{SubmitTransactionRequest, SubmitTransactionResult};
-use base::karma_coin::karma_coin_core_types::TransactionType::NewUserV1;
-use base::karma_coin::karma_coin_core_types::{
-    AccountId, KeyPair, MobileNumber, TransactionBody, VerificationResult,
-};
-use base::karma_coin::karma_coin_core_types::{
-    NewUserTransactionV1, SignedTransaction, TransactionData,
-};
+pub mod twilio_stub;
+
 use base::karma_coin::karma_coin_verifier::verifier_service_client::VerifierServiceClient;
-use base::karma_coin::karma_coin_verifier::VerifyNumberRequest;
-use base::signed_trait::SignedTrait;
+use base::karma_coin::karma_coin_verifier::{
+    EncryptedPayload, GetPreKeyBundleRequest, GetVerifierInfoRequest, PreKeyBundle,
+    SendVerificationCodeRequest, SendVerificationCodeResponse, VerifierInfo, VerifyEvidenceRequest,
+    VerifyEvidenceResponse, VerifyNumberRequest, VerifyNumberRequestData, VerifyNumberResponse,
+};
+use base::server_config_service::{ServerConfigService, SetConfigFile};
 use base::tests_helpers::enable_logger;
-use bytes::Bytes;
 use chrono::Utc;
-use db::db_service::DatabaseService;
+use crypto::x2dh::{encrypt_alice, execute_alice, ProtocolInputAlice};
 use log::info;
 use prost::Message;
+use server::server_service::{Reload, ServerService, Startup};
+use sp_core::crypto::Ss58Codec;
+use sp_core::ed25519::Pair;
+use sp_core::Pair as PairT;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tonic::transport::Channel;
+use twilio_stub::TwilioStub;
 use xactor::*;
 
-// helper function to create a new user
+/// Verifier identity used by all tests
+#[allow(dead_code)]
+pub const VERIFIER_MNEMONIC: &str =
+    "bottom drive obey lake curtain smoke basket hold race lonely fit walk";
 #[allow(dead_code)]
-pub async fn create_user(
-    user_name: String,
-    number: String,
-) -> Result<(KeyPair, MobileNumber, Bytes)> {
-    let user_key_pair = KeyPair::new();
-    let user_ed_key_pair = user_key_pair.to_ed2559_keypair();
-    let account_id_bytes = user_ed_key_pair.public.to_bytes().to_vec();
+pub const VERIFIER_KEY_PASSWORD: &str = "test";
+#[allow(dead_code)]
+pub const BYPASS_TOKEN: &str = "test-bypass-token";
+/// Mobile number verified by tests
+#[allow(dead_code)]
+pub const MOBILE_NUMBER: &str = "+14155552671";
+/// Twilio verification session id returned by the stub.
+/// Differs from the sequential session ids of the mock provider so tests can tell the providers apart
+#[allow(dead_code)]
+pub const SID: &str = "VEf00d000000000000000000000000beef";
+/// Verifier config lines which disable rate limits for tests which send many requests
+#[allow(dead_code)]
+pub const NO_RATE_LIMITS: &str =
+    "  rate_limits: {phone_number: {capacity: 0}, account: {capacity: 0}, client_ip: {capacity: 0}}";
+
+// Services are registry singletons bound to the runtime that started them,
+// so all tests in a test binary share one runtime and one verifier server.
+static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+static TEST_ENV: OnceLock<TestEnv> = OnceLock::new();
+static TEST_LOCK: Mutex<()> = Mutex::new(());
 
-    let mobile_number = MobileNumber { number };
-    let account_id = AccountId {
-        data: account_id_bytes.clone(),
-    };
+/// Runs a test on the shared runtime. Tests are serialized as they share the server and the stub.
+#[allow(dead_code)]
+pub fn run_test<F: Future>(test: F) -> F::Output {
+    let _guard = TEST_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    RUNTIME
+        .get_or_init(|| {
+            tokio::runtime::Builder::new_multi_thread()
+                .enable_all()
+                .build()
+                .expect("failed to create tests runtime")
+        })
+        .block_on(test)
+}
 
-    let mut verifier_service_client = VerifierServiceClient::connect("http://127.0.0.1:9080")
-        .await
-        .unwrap();
+/// A verifier server configured to use a twilio stub
+pub struct TestEnv {
+    pub stub: TwilioStub,
+    client: VerifierServiceClient<Channel>,
+    /// address the verifier's grpc server is bound to
+    addr: SocketAddr,
+    config_file: PathBuf,
+}
 
-    let mut v_request = VerifyNumberRequest::new();
+/// Writes a verifier config file. `verifier_config` lines are added to the verifier section of the config yaml.
+/// The grpc server binds any free port so test binaries can run in parallel.
+fn write_config(config_file: &Path, base_url: &str, verifier_config: &str) {
+    let config = format!(
+        r#"
+server_name: Verifier tests
+grpc_host: 127.0.0.1
+grpc_host_port: 0
 
-    v_request.mobile_number = Some(mobile_number.clone());
-    v_request.account_id = Some(account_id.clone());
-    v_request.requested_user_name = user_name.clone();
-    v_request.signature = Some(v_request.sign(&user_ed_key_pair).unwrap());
+verifier:
+  name: Test Verifier
+  key_password: {password}
+  key_mnemonic: {mnemonic}
+  bypass_token: {bypass_token}
+  provider: twilio
+{verifier_config}
 
-    v_request
-        .verify_signature()
-        .expect("signature verification failed");
+twilio:
+  account_sid: ACtest
+  auth_token: test-auth-token
+  service_id: VAtest
+  base_url: {base_url}
+  timeout_secs: 1
 
-    let resp1 = verifier_service_client.verify_number(v_request).await?;
+mock:
+  enabled: true
+"#,
+        password = VERIFIER_KEY_PASSWORD,
+        mnemonic = VERIFIER_MNEMONIC,
+        bypass_token = BYPASS_TOKEN,
+        base_url = base_url,
+        verifier_config = verifier_config,
+    );
 
-    let v_resp = resp1.into_inner();
+    std::fs::write(config_file, config).expect("failed to write test config file");
+}
 
-    let data = v_resp.user_verification_data.unwrap();
+impl TestEnv {
+    /// Returns the test binary's env, starting the stub and the server on first use.
+    /// `verifier_config` lines are added to the verifier section of the config yaml.
+    #[allow(dead_code)]
+    pub async fn get(verifier_config: &str) -> &'static TestEnv {
+        if let Some(env) = TEST_ENV.get() {
+            env.stub.reset();
+            return env;
+        }
 
-    data.verify_signature().expect("invalid evidence signature");
-    if data.verification_result != VerificationResult::Verified as i32 {
-        return Err(anyhow!("phone verification failed"));
+        let env = TestEnv::start(verifier_config).await;
+        TEST_ENV.get_or_init(|| env)
     }
 
-    info!("verify evidence verified");
+    async fn start(verifier_config: &str) -> TestEnv {
+        enable_logger();
 
-    let new_user_tx = NewUserTransactionV1 {
-        verify_number_response: Some(data.clone()),
-    };
+        let stub = TwilioStub::start().await;
+        let config_file =
+            std::env::temp_dir().join(format!("verifier_test_{}.yaml", std::process::id()));
+        write_config(&config_file, &stub.base_url(), verifier_config);
 
-    let mut buf = Vec::with_capacity(new_user_tx.encoded_len());
-    new_user_tx.encode(&mut buf).unwrap();
+        ServerConfigService::from_registry()
+            .await
+            .unwrap()
+            .call(SetConfigFile {
+                config_file: config_file.to_str().unwrap().into(),
+            })
+            .await
+            .unwrap()
+            .unwrap();
 
-    let net_id = GenesisConfigService::get_u64(NET_ID_KEY.into())
-        .await
-        .unwrap()
-        .unwrap() as u32;
+        let server = ServerService::from_registry().await.unwrap();
+        let addr = server.call(Startup {}).await.unwrap().unwrap();
 
-    let tx_body = TransactionBody {
-        timestamp: Utc::now().timestamp_millis() as u64,
-        nonce: 1,
-        fee: 1,
-        transaction_data: Some(TransactionData {
-            transaction_data: buf,
-            transaction_type: NewUserV1 as i32,
-        }),
-        net_id,
-    };
+        // the grpc server is started in the background
+        let url = format!("http://{}", addr);
+        let mut attempts = 0;
+        let client = loop {
+            match VerifierServiceClient::connect(url.clone()).await {
+                Ok(client) => break client,
+                Err(e) => {
+                    attempts += 1;
+                    if attempts > 50 {
+                        panic!("failed to connect to verifier: {}", e);
+                    }
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+            }
+        };
 
-    let mut buf1 = Vec::with_capacity(tx_body.encoded_len());
-    tx_body.encode(&mut buf1).unwrap();
+        info!("test env started");
+        TestEnv {
+            stub,
+            client,
+            addr,
+            config_file,
+        }
+    }
 
-    let mut signed_tx = SignedTransaction {
-        signer: Some(account_id.clone()),
-        transaction_body: buf1,
-        signature: None,
-    };
+    /// Rewrites the config file with the provided verifier config lines and reloads the server's config
+    #[allow(dead_code)]
+    pub async fn reload(&self, verifier_config: &str) -> Result<()> {
+        write_config(&self.config_file, &self.stub.base_url(), verifier_config);
 
-    signed_tx.signature = Some(signed_tx.sign(&user_ed_key_pair).unwrap());
+        let server = ServerService::from_registry().await?;
+        server.call(Reload).await?
+    }
 
-    signed_tx.validate().await.expect("invalid transaction");
-    info!("new user tx signature's valid");
+    /// Returns the address the verifier's grpc server is bound to
+    #[allow(dead_code)]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
 
-    let mut api_client = ApiServiceClient::connect("http://[::1]:9080")
-        .await
-        .unwrap();
+    /// Returns a client of the verifier's api for tests which check grpc errors
+    #[allow(dead_code)]
+    pub fn client(&self) -> VerifierServiceClient<Channel> {
+        self.client.clone()
+    }
 
-    let resp = api_client
-        .submit_transaction(SubmitTransactionRequest {
-            transaction: Some(signed_tx.clone()),
-        })
-        .await?
-        .into_inner();
+    /// Calls the verifier's SendVerificationCode api
+    #[allow(dead_code)]
+    pub async fn send_verification_code(
+        &self,
+        request: SendVerificationCodeRequest,
+    ) -> SendVerificationCodeResponse {
+        self.client
+            .clone()
+            .send_verification_code(request)
+            .await
+            .expect("send verification code api call failed")
+            .into_inner()
+    }
+
+    /// Calls the verifier's VerifyNumber api
+    #[allow(dead_code)]
+    pub async fn verify_number(&self, request: VerifyNumberRequest) -> VerifyNumberResponse {
+        self.client
+            .clone()
+            .verify_number(request)
+            .await
+            .expect("verify number api call failed")
+            .into_inner()
+    }
+
+    /// Calls the verifier's VerifyEvidence api
+    #[allow(dead_code)]
+    pub async fn verify_evidence(
+        &self,
+        evidence: Vec<u8>,
+        signature: Vec<u8>,
+    ) -> VerifyEvidenceResponse {
+        self.client
+            .clone()
+            .verify_evidence(VerifyEvidenceRequest {
+                evidence,
+                signature,
+            })
+            .await
+            .expect("verify evidence api call failed")
+            .into_inner()
+    }
 
-    if resp.submit_transaction_result != SubmitTransactionResult::Submitted as i32 {
-        return Err(anyhow!("Transaction rejected"));
+    /// Calls the verifier's GetVerifierInfo api
+    #[allow(dead_code)]
+    pub async fn get_verifier_info(&self) -> VerifierInfo {
+        self.client
+            .clone()
+            .get_verifier_info(GetVerifierInfoRequest {})
+            .await
+            .expect("get verifier info api call failed")
+            .into_inner()
+            .verifier_info
+            .expect("missing verifier info")
     }
 
-    let tx_hash = signed_tx.get_hash().unwrap();
+    /// Calls the verifier's GetPreKeyBundle api
+    #[allow(dead_code)]
+    pub async fn get_pre_key_bundle(&self) -> PreKeyBundle {
+        self.client
+            .clone()
+            .get_pre_key_bundle(GetPreKeyBundleRequest {})
+            .await
+            .expect("get pre-key bundle api call failed")
+            .into_inner()
+            .pre_key_bundle
+            .expect("missing pre-key bundle")
+    }
+}
 
-    Ok((user_key_pair, mobile_number, tx_hash))
+/// Encrypts a payload to a pre-key bundle as clients do
+#[allow(dead_code)]
+pub fn encrypt_to_bundle(bundle: &PreKeyBundle, plaintext: &[u8]) -> EncryptedPayload {
+    let pre_key: [u8; 32] = bundle.pre_key.as_slice().try_into().unwrap();
+    let output = execute_alice(&ProtocolInputAlice {
+        ikb: ed25519_dalek::PublicKey::from_bytes(&bundle.identity_key).unwrap(),
+        pkb: x25519_dalek::PublicKey::from(pre_key),
+        b_bundle_id: bundle.bundle_id,
+    });
+    EncryptedPayload {
+        bundle_id: bundle.bundle_id,
+        ephemeral_key: output.eka.as_bytes().to_vec(),
+        ciphertext: encrypt_alice(&output, plaintext).unwrap().to_vec(),
+    }
 }
 
-/// Helper
+/// Returns the public key of the verifier's identity
 #[allow(dead_code)]
-pub async fn init_test() {
-    enable_logger();
+pub fn verifier_public_key() -> sp_core::ed25519::Public {
+    let (pair, _) = Pair::from_phrase(VERIFIER_MNEMONIC, Some(VERIFIER_KEY_PASSWORD))
+        .expect("invalid verifier mnemonic");
+    pair.public()
 }
 
-/// Helper
+/// A user account used to sign verify number requests
 #[allow(dead_code)]
-pub async fn finalize_test() {
-    spawn(async {
-        // stop the db so it has a chance to destroy itself if it is configured to destroy storage on stop...
-        let mut db_service = DatabaseService::from_registry().await.unwrap();
-        let _ = db_service.stop(None);
-        info!("resources cleanup completed");
-    })
-    .await
-    .unwrap();
+pub struct TestUser {
+    pub pair: Pair,
+}
+
+#[allow(dead_code)]
+impl TestUser {
+    /// Creates a user with a random key pair
+    pub fn generate() -> Self {
+        let (pair, _) = Pair::generate();
+        TestUser { pair }
+    }
+
+    /// Returns the user's ss58 account id
+    pub fn account_id(&self) -> String {
+        self.pair.public().to_ss58check()
+    }
+
+    /// Returns verify number request data for this user with the current timestamp
+    pub fn request_data(
+        &self,
+        phone_number: &str,
+        verification_code: &str,
+        verification_sid: &str,
+    ) -> VerifyNumberRequestData {
+        VerifyNumberRequestData {
+            timestamp: Utc::now().timestamp_millis() as u64,
+            account_id: self.account_id(),
+            phone_number: phone_number.into(),
+            user_name: "tester".into(),
+            bypass_token: "".into(),
+            verification_code: verification_code.into(),
+            verification_sid: verification_sid.into(),
+        }
+    }
+
+    /// Encodes and signs request data
+    pub fn sign_request(&self, data: &VerifyNumberRequestData) -> VerifyNumberRequest {
+        let data = data.encode_to_vec();
+        let signature = self.pair.sign(&data);
+        VerifyNumberRequest {
+            data,
+            signature: signature.0.to_vec(),
+            encrypted_data: None,
+        }
+    }
 }
@@ -0,0 +1,227 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! A local Twilio Verify compatible http server for contract tests.
+//! Tests script the responses of each endpoint and inspect the requests the verifier made.
+
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use log::{error, info};
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Twilio Verify endpoints served by the stub
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    /// POST /v2/Services/{sid}/Verifications
+    Verifications,
+    /// POST /v2/Services/{sid}/VerificationCheck
+    VerificationCheck,
+    /// POST /v2/Services/{sid}/Verifications/{verification_sid}
+    UpdateVerification,
+    Unknown,
+}
+
+impl Endpoint {
+    fn from_path(path: &str) -> Endpoint {
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match parts.as_slice() {
+            ["v2", "Services", _, "Verifications"] => Endpoint::Verifications,
+            ["v2", "Services", _, "VerificationCheck"] => Endpoint::VerificationCheck,
+            ["v2", "Services", _, "Verifications", _] => Endpoint::UpdateVerification,
+            _ => Endpoint::Unknown,
+        }
+    }
+}
+
+/// A scripted stub response
+#[derive(Debug, Clone)]
+pub struct StubResponse {
+    pub status: u16,
+    pub body: String,
+    /// Time to wait before responding - used to trigger client timeouts
+    pub delay: Option<Duration>,
+}
+
+impl StubResponse {
+    /// 201 response to a new verification request
+    #[allow(dead_code)]
+    pub fn verification_created(sid: &str) -> Self {
+        StubResponse {
+            status: 201,
+            body: format!(r#"{{"sid":"{}","status":"pending"}}"#, sid),
+            delay: None,
+        }
+    }
+
+    /// 200 response to a verification check with the provided session status
+    #[allow(dead_code)]
+    pub fn verification_check(sid: &str, status: &str) -> Self {
+        StubResponse {
+            status: 200,
+            body: format!(r#"{{"sid":"{}","status":"{}"}}"#, sid, status),
+            delay: None,
+        }
+    }
+
+    /// Twilio style error response with the provided http status code
+    #[allow(dead_code)]
+    pub fn error(status: u16) -> Self {
+        StubResponse {
+            status,
+            body: format!(
+                r#"{{"code":60200,"message":"stub error","more_info":"","status":{}}}"#,
+                status
+            ),
+            delay: None,
+        }
+    }
+
+    /// Response with the provided status code and a body which isn't json
+    #[allow(dead_code)]
+    pub fn malformed(status: u16) -> Self {
+        StubResponse {
+            status,
+            body: "<html>not json</html>".into(),
+            delay: None,
+        }
+    }
+
+    /// Returns this response after waiting for the provided duration
+    #[allow(dead_code)]
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+}
+
+/// A request received by the stub
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub endpoint: Endpoint,
+    pub path: String,
+    pub authorization: Option<String>,
+    /// url encoded form body
+    pub body: String,
+}
+
+#[derive(Default)]
+struct StubState {
+    responses: HashMap<Endpoint, VecDeque<StubResponse>>,
+    requests: Vec<RecordedRequest>,
+}
+
+/// Twilio Verify stub server
+pub struct TwilioStub {
+    addr: SocketAddr,
+    state: Arc<Mutex<StubState>>,
+}
+
+impl TwilioStub {
+    /// Starts the stub on a random local port
+    pub async fn start() -> TwilioStub {
+        let state = Arc::new(Mutex::new(StubState::default()));
+        let service_state = Arc::clone(&state);
+
+        let make_service = make_service_fn(move |_conn| {
+            let state = Arc::clone(&service_state);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |req| {
+                    handle_request(Arc::clone(&state), req)
+                }))
+            }
+        });
+
+        let server = Server::bind(&([127, 0, 0, 1], 0).into()).serve(make_service);
+        let addr = server.local_addr();
+
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!("twilio stub stopped due to error: {}", e);
+            }
+        });
+
+        info!("twilio stub listening on: {}", addr);
+        TwilioStub { addr, state }
+    }
+
+    /// Returns the url to configure as the verifier's twilio base url
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Adds a response to be returned by the next unanswered call to an endpoint
+    #[allow(dead_code)]
+    pub fn push_response(&self, endpoint: Endpoint, response: StubResponse) {
+        self.state
+            .lock()
+            .unwrap()
+            .responses
+            .entry(endpoint)
+            .or_default()
+            .push_back(response);
+    }
+
+    /// Returns all requests received since the last reset
+    #[allow(dead_code)]
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Clears scripted responses and recorded requests
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.responses.clear();
+        state.requests.clear();
+    }
+}
+
+async fn handle_request(
+    state: Arc<Mutex<StubState>>,
+    req: Request<Body>,
+) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path().to_string();
+    let endpoint = Endpoint::from_path(&path);
+    let authorization = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(String::from);
+
+    let body = hyper::body::to_bytes(req.into_body())
+        .await
+        .unwrap_or_default();
+
+    let scripted = {
+        let mut state = state.lock().unwrap();
+        state.requests.push(RecordedRequest {
+            endpoint,
+            path,
+            authorization,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        });
+        state
+            .responses
+            .get_mut(&endpoint)
+            .and_then(|responses| responses.pop_front())
+    };
+
+    // unscripted calls fail so tests notice unexpected provider calls
+    let response = scripted.unwrap_or_else(|| StubResponse::error(500));
+
+    if let Some(delay) = response.delay {
+        tokio::time::sleep(delay).await;
+    }
+
+    Ok(Response::builder()
+        .status(response.status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(response.body))
+        .unwrap())
+}
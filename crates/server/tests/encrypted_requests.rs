@@ -0,0 +1,200 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of requests encrypted to the verifier's X2DH pre-key bundle
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    EncryptedPayload, PreKeyBundle, SendVerificationCodeRequest, SendVerificationCodeResult,
+    VerificationChannel, VerificationResult, VerifyNumberRequest,
+};
+use common::twilio_stub::{Endpoint, StubResponse};
+use common::{
+    encrypt_to_bundle, run_test, verifier_public_key, TestEnv, TestUser, BYPASS_TOKEN,
+    MOBILE_NUMBER, NO_RATE_LIMITS, SID,
+};
+use prost::Message;
+use sp_core::ed25519::{Pair, Signature};
+use sp_core::Pair as PairT;
+
+async fn env() -> &'static TestEnv {
+    TestEnv::get(NO_RATE_LIMITS).await
+}
+
+async fn pre_key_bundle(env: &TestEnv) -> PreKeyBundle {
+    env.get_pre_key_bundle().await
+}
+
+fn encrypted_send_request(payload: EncryptedPayload) -> SendVerificationCodeRequest {
+    SendVerificationCodeRequest {
+        mobile_number: "".into(),
+        channel: VerificationChannel::Unspecified as i32,
+        encrypted_request: Some(payload),
+    }
+}
+
+/// Returns a bypass token verify number request of a new user with data encrypted to the bundle
+fn encrypted_verify_request(bundle: &PreKeyBundle) -> VerifyNumberRequest {
+    let user = TestUser::generate();
+    let mut data = user.request_data(MOBILE_NUMBER, "", "");
+    data.bypass_token = BYPASS_TOKEN.into();
+    let mut request = user.sign_request(&data);
+    request.encrypted_data = Some(encrypt_to_bundle(bundle, &request.data));
+    request.data = vec![];
+    request
+}
+
+#[test]
+fn pre_key_bundle_signed_by_verifier() {
+    run_test(async {
+        let env = env().await;
+        let bundle = pre_key_bundle(env).await;
+
+        assert_eq!(bundle.verifier_public_key, verifier_public_key().0.to_vec());
+        assert!(bundle.created_at < bundle.expires_at);
+        let bundle_id = bundle.bundle_id.to_le_bytes();
+        let expires_at = bundle.expires_at.to_le_bytes();
+        let signed_data: [&[u8]; 5] = [
+            b"karmacoin-pre-key-bundle",
+            &bundle_id,
+            &bundle.identity_key,
+            &bundle.pre_key,
+            &expires_at,
+        ];
+        let signed_data = signed_data.concat();
+        let signature = Signature::from_slice(&bundle.signature).expect("invalid signature");
+        assert!(Pair::verify(
+            &signature,
+            &signed_data,
+            &verifier_public_key()
+        ));
+    })
+}
+
+#[test]
+fn verifier_info_has_pre_key_bundle() {
+    run_test(async {
+        let env = env().await;
+        let bundle = pre_key_bundle(env).await;
+
+        let info_bundle = env
+            .get_verifier_info()
+            .await
+            .pre_key_bundle
+            .expect("missing pre-key bundle");
+        assert_eq!(info_bundle, bundle);
+    })
+}
+
+#[test]
+fn encrypted_send_code() {
+    run_test(async {
+        let env = env().await;
+        let bundle = pre_key_bundle(env).await;
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID),
+        );
+
+        let plaintext = SendVerificationCodeRequest {
+            mobile_number: MOBILE_NUMBER.into(),
+            channel: VerificationChannel::Sms as i32,
+            encrypted_request: None,
+        };
+        let resp = env
+            .send_verification_code(encrypted_send_request(encrypt_to_bundle(
+                &bundle,
+                &plaintext.encode_to_vec(),
+            )))
+            .await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+        assert_eq!(resp.channel, VerificationChannel::Sms as i32);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].body.contains("To=%2B14155552671"));
+    })
+}
+
+#[test]
+fn encrypted_send_code_with_plaintext_number_rejected() {
+    run_test(async {
+        let env = env().await;
+        let bundle = pre_key_bundle(env).await;
+
+        let mut request = encrypted_send_request(encrypt_to_bundle(&bundle, &[]));
+        request.mobile_number = MOBILE_NUMBER.into();
+        let resp = env.send_verification_code(request).await;
+        assert_eq!(
+            resp.result,
+            SendVerificationCodeResult::InvalidUserData as i32
+        );
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn encrypted_send_code_unknown_bundle() {
+    run_test(async {
+        let env = env().await;
+        let mut bundle = pre_key_bundle(env).await;
+        bundle.bundle_id += 1;
+
+        let plaintext = SendVerificationCodeRequest {
+            mobile_number: MOBILE_NUMBER.into(),
+            channel: VerificationChannel::Unspecified as i32,
+            encrypted_request: None,
+        };
+        let resp = env
+            .send_verification_code(encrypted_send_request(encrypt_to_bundle(
+                &bundle,
+                &plaintext.encode_to_vec(),
+            )))
+            .await;
+        assert_eq!(
+            resp.result,
+            SendVerificationCodeResult::InvalidEncryption as i32
+        );
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn encrypted_verify_number() {
+    run_test(async {
+        let env = env().await;
+        let bundle = pre_key_bundle(env).await;
+
+        let resp = env.verify_number(encrypted_verify_request(&bundle)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+        assert!(resp.user_verification_data.is_some());
+    })
+}
+
+#[test]
+fn encrypted_verify_number_tampered() {
+    run_test(async {
+        let env = env().await;
+        let bundle = pre_key_bundle(env).await;
+
+        let mut request = encrypted_verify_request(&bundle);
+        request.encrypted_data.as_mut().unwrap().ciphertext[0] ^= 1;
+        let resp = env.verify_number(request).await;
+        assert_eq!(resp.result, VerificationResult::InvalidEncryption as i32);
+    })
+}
+
+#[test]
+fn encrypted_verify_number_with_plaintext_data_rejected() {
+    run_test(async {
+        let env = env().await;
+        let bundle = pre_key_bundle(env).await;
+
+        let mut request = encrypted_verify_request(&bundle);
+        request.data = vec![1];
+        let resp = env.verify_number(request).await;
+        assert_eq!(resp.result, VerificationResult::InvalidEncryption as i32);
+    })
+}
@@ -0,0 +1,206 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of signing evidence with rotated verifier keys
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    EvidenceStatus, PhoneNumberHashScheme, VerificationResult,
+};
+use base::server_config_service::{
+    ServerConfigService, DEFAULT_VERIFIER_EVIDENCE_TTL_SECS, VERIFIER_EVIDENCE_TTL_SECS_CONFIG_KEY,
+};
+use chrono::Utc;
+use common::{run_test, TestEnv, TestUser, BYPASS_TOKEN, MOBILE_NUMBER, NO_RATE_LIMITS};
+use parity_scale_codec::Encode;
+use sp_core::crypto::AccountId32;
+use sp_core::ed25519::Pair as Ed25519Pair;
+use sp_core::Pair;
+
+/// active since the epoch and not retired - overlaps with NEW_KEY
+const OLD_KEY: &str = "legal winner thank year wave sausage worth useful legal winner thank yellow";
+/// the most recently activated key
+const NEW_KEY: &str =
+    "letter advice cage absurd amount doctor acoustic avoid letter advice cage above";
+/// retired a second after the epoch
+const RETIRED_KEY: &str = "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong";
+/// activated in 2100
+const FUTURE_KEY: &str =
+    "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+fn keys_config() -> String {
+    format!(
+        r#"  keys:
+    - name: old
+      mnemonic: {}
+    - name: new
+      mnemonic: {}
+      active_from: 2000
+    - name: retired
+      mnemonic: {}
+      retire_at: 1000
+    - name: future
+      mnemonic: {}
+      active_from: 4102444800000
+{}"#,
+        OLD_KEY, NEW_KEY, RETIRED_KEY, FUTURE_KEY, NO_RATE_LIMITS
+    )
+}
+
+async fn env() -> &'static TestEnv {
+    TestEnv::get(&keys_config()).await
+}
+
+fn key_pair(phrase: &str) -> Ed25519Pair {
+    Ed25519Pair::from_phrase(phrase, None)
+        .expect("invalid key mnemonic")
+        .0
+}
+
+/// Returns unexpired scale encoded evidence signed by the provided key for a new user
+fn signed_evidence(verifier: &Ed25519Pair) -> (Vec<u8>, Vec<u8>) {
+    let user = TestUser::generate();
+    let issued_at = Utc::now().timestamp_millis() as u64;
+    let evidence = (
+        verifier.public(),
+        AccountId32::from(user.pair.public()),
+        "tester".to_string(),
+        sp_core::hashing::blake2_512(MOBILE_NUMBER.as_bytes()),
+        issued_at,
+        issued_at + 60 * 1000,
+        PhoneNumberHashScheme::Blake2512 as u8,
+    )
+        .encode();
+    let signature = verifier.sign(&evidence).0.to_vec();
+    (evidence, signature)
+}
+
+/// Verifies MOBILE_NUMBER with the bypass token and returns the public key of the signing key
+async fn signing_public_key(env: &TestEnv) -> Vec<u8> {
+    let user = TestUser::generate();
+    let mut data = user.request_data(MOBILE_NUMBER, "", "");
+    data.bypass_token = BYPASS_TOKEN.into();
+    let resp = env.verify_number(user.sign_request(&data)).await;
+    assert_eq!(resp.result, VerificationResult::Verified as i32);
+    resp.user_verification_data
+        .expect("missing evidence")
+        .verifier_public_key
+}
+
+#[test]
+fn signs_with_most_recently_activated_key() {
+    run_test(async {
+        let env = env().await;
+        assert_eq!(
+            signing_public_key(env).await,
+            key_pair(NEW_KEY).public().0.to_vec()
+        );
+
+        let (evidence, signature) = signed_evidence(&key_pair(NEW_KEY));
+        let resp = env.verify_evidence(evidence, signature).await;
+        assert_eq!(resp.status, EvidenceStatus::Valid as i32);
+        assert!(resp.signed_by_current_key);
+    })
+}
+
+#[test]
+fn previously_active_keys_verify() {
+    run_test(async {
+        let env = env().await;
+        for phrase in [OLD_KEY, RETIRED_KEY] {
+            let (evidence, signature) = signed_evidence(&key_pair(phrase));
+            let resp = env.verify_evidence(evidence, signature).await;
+            assert_eq!(resp.status, EvidenceStatus::Valid as i32);
+            assert!(!resp.signed_by_current_key);
+        }
+    })
+}
+
+#[test]
+fn future_key_does_not_verify() {
+    run_test(async {
+        let env = env().await;
+        let (evidence, signature) = signed_evidence(&key_pair(FUTURE_KEY));
+        let resp = env.verify_evidence(evidence, signature).await;
+        assert_eq!(resp.status, EvidenceStatus::InvalidSignature as i32);
+    })
+}
+
+#[test]
+fn verifier_info_keys() {
+    run_test(async {
+        let env = env().await;
+        let info = env.get_verifier_info().await;
+        assert_eq!(info.public_key, key_pair(NEW_KEY).public().0.to_vec());
+        assert_eq!(
+            info.past_public_keys,
+            vec![
+                key_pair(OLD_KEY).public().0.to_vec(),
+                key_pair(RETIRED_KEY).public().0.to_vec()
+            ]
+        );
+    })
+}
+
+#[test]
+fn reload_rotates_keys() {
+    run_test(async {
+        let env = env().await;
+
+        // activate the future key now
+        let rotated_config = keys_config().replace("4102444800000", "3000");
+        env.reload(&rotated_config)
+            .await
+            .expect("failed to reload keys");
+        let rotated_public_key = signing_public_key(env).await;
+
+        // keys with invalid validity windows are rejected and the current keys are kept
+        let invalid_config = keys_config().replace("retire_at: 1000", "retire_at: 0");
+        let invalid_reload = env.reload(&invalid_config).await;
+        let kept_public_key = signing_public_key(env).await;
+
+        env.reload(&keys_config())
+            .await
+            .expect("failed to reload keys");
+
+        assert_eq!(rotated_public_key, key_pair(FUTURE_KEY).public().0.to_vec());
+        assert!(invalid_reload.is_err());
+        assert_eq!(kept_public_key, key_pair(FUTURE_KEY).public().0.to_vec());
+        assert_eq!(
+            signing_public_key(env).await,
+            key_pair(NEW_KEY).public().0.to_vec()
+        );
+    })
+}
+
+#[test]
+fn reload_only_reloads_keys() {
+    run_test(async {
+        let env = env().await;
+
+        let rotated_config = format!(
+            "{}\n  evidence_ttl_secs: 60",
+            keys_config().replace("4102444800000", "3000")
+        );
+        env.reload(&rotated_config)
+            .await
+            .expect("failed to reload keys");
+        let rotated_public_key = signing_public_key(env).await;
+        let evidence_ttl_secs =
+            ServerConfigService::get_u64(VERIFIER_EVIDENCE_TTL_SECS_CONFIG_KEY.into())
+                .await
+                .unwrap();
+
+        env.reload(&keys_config())
+            .await
+            .expect("failed to reload keys");
+
+        assert_eq!(rotated_public_key, key_pair(FUTURE_KEY).public().0.to_vec());
+        assert_eq!(
+            evidence_ttl_secs,
+            Some(DEFAULT_VERIFIER_EVIDENCE_TTL_SECS as u64)
+        );
+    })
+}
@@ -0,0 +1,85 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of verifier keystore files
+
+use server::keystore::Keystore;
+use sp_core::ed25519::Pair;
+use sp_core::Pair as PairT;
+
+const PASSWORD: &str = "keystore-test-password";
+
+#[test]
+fn keystore_round_trip() {
+    let (pair, _) = Pair::generate();
+    let path = std::env::temp_dir().join("verifier_keystore_round_trip.json");
+
+    Keystore::create(&pair, PASSWORD)
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let keystore = Keystore::load(&path).unwrap();
+
+    // the public key is available without the password
+    assert_eq!(keystore.public_key().unwrap(), pair.public());
+    assert_eq!(keystore.unlock(PASSWORD).unwrap().public(), pair.public());
+    assert!(keystore.unlock("wrong-password").is_err());
+}
+
+#[test]
+fn keystore_public_key_is_authenticated() {
+    let (pair, _) = Pair::generate();
+    let (other_pair, _) = Pair::generate();
+    let keystore = Keystore::create(&pair, PASSWORD).unwrap();
+
+    let mut json = serde_json::to_value(&keystore).unwrap();
+    json["public_key"] = hex::encode(other_pair.public().0).into();
+    let tampered: Keystore = serde_json::from_value(json).unwrap();
+
+    assert_eq!(tampered.public_key().unwrap(), other_pair.public());
+    assert!(tampered.unlock(PASSWORD).is_err());
+}
+
+#[test]
+fn keystore_unsupported_version() {
+    let (pair, _) = Pair::generate();
+    let keystore = Keystore::create(&pair, PASSWORD).unwrap();
+    let mut json = serde_json::to_value(&keystore).unwrap();
+    json["version"] = 3.into();
+
+    let path = std::env::temp_dir().join("verifier_keystore_unsupported_version.json");
+    std::fs::write(&path, json.to_string()).unwrap();
+    assert!(Keystore::load(&path).is_err());
+}
+
+#[test]
+fn keystore_version_is_authenticated() {
+    let (pair, _) = Pair::generate();
+    let keystore = Keystore::create(&pair, PASSWORD).unwrap();
+    assert!(!keystore.is_legacy());
+
+    // a current keystore doesn't unlock as a legacy keystore
+    let mut json = serde_json::to_value(&keystore).unwrap();
+    json["version"] = 1.into();
+    let legacy: Keystore = serde_json::from_value(json).unwrap();
+    assert!(legacy.is_legacy());
+    assert!(legacy.unlock(PASSWORD).is_err());
+}
+
+#[test]
+fn keystore_change_password() {
+    let (pair, _) = Pair::generate();
+    let keystore = Keystore::create(&pair, PASSWORD).unwrap();
+    assert!(keystore
+        .change_password("wrong-password", "new-password")
+        .is_err());
+
+    let changed = keystore.change_password(PASSWORD, "new-password").unwrap();
+    assert_eq!(changed.public_key().unwrap(), pair.public());
+    assert_eq!(
+        changed.unlock("new-password").unwrap().public(),
+        pair.public()
+    );
+    assert!(changed.unlock(PASSWORD).is_err());
+}
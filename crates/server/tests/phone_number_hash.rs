@@ -0,0 +1,97 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of issuing evidence with peppered phone number hashes
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    EvidenceStatus, PhoneNumberHashScheme, VerificationResult,
+};
+use common::{
+    run_test, verifier_public_key, TestEnv, TestUser, BYPASS_TOKEN, MOBILE_NUMBER, NO_RATE_LIMITS,
+};
+use crypto::hmacer::Hmacer;
+use parity_scale_codec::Encode;
+use sp_core::crypto::AccountId32;
+use sp_core::Pair as PairT;
+
+const PEPPER: [u8; 32] = [7; 32];
+
+async fn env() -> &'static TestEnv {
+    let config = format!(
+        "  phone_number_hash: {{scheme: hmac_sha512, pepper: \"{}\"}}\n{}",
+        hex::encode(PEPPER),
+        NO_RATE_LIMITS
+    );
+    TestEnv::get(&config).await
+}
+
+fn peppered_hash(phone_number: &str) -> [u8; 64] {
+    Hmacer::hmac_sha512(&PEPPER, phone_number.as_bytes())
+        .unwrap()
+        .unprotected_as_bytes()
+        .try_into()
+        .unwrap()
+}
+
+#[test]
+fn evidence_has_peppered_phone_number_hash() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+
+        // the hash is tagged with the hmac sha512 scheme
+        let expected_evidence = (
+            verifier_public_key(),
+            AccountId32::from(user.pair.public()),
+            "tester".to_string(),
+            peppered_hash(MOBILE_NUMBER),
+            resp.issued_at,
+            resp.expires_at,
+            PhoneNumberHashScheme::HmacSha512 as u8,
+        )
+            .encode();
+        let user_verification_data = resp.user_verification_data.expect("missing evidence");
+        assert_eq!(user_verification_data.evidence, expected_evidence);
+        assert_ne!(
+            peppered_hash(MOBILE_NUMBER),
+            sp_core::hashing::blake2_512(MOBILE_NUMBER.as_bytes())
+        );
+
+        let resp = env
+            .verify_evidence(
+                user_verification_data.evidence,
+                user_verification_data.signature,
+            )
+            .await;
+        assert_eq!(resp.status, EvidenceStatus::Valid as i32);
+        assert_eq!(
+            resp.phone_number_hash,
+            peppered_hash(MOBILE_NUMBER).to_vec()
+        );
+        assert_eq!(
+            resp.phone_number_hash_scheme,
+            PhoneNumberHashScheme::HmacSha512 as i32
+        );
+    })
+}
+
+#[test]
+fn verifier_info_has_phone_number_hash_scheme() {
+    run_test(async {
+        let env = env().await;
+
+        let info = env.get_verifier_info().await;
+        assert_eq!(
+            info.phone_number_hash_scheme,
+            PhoneNumberHashScheme::HmacSha512 as i32
+        );
+    })
+}
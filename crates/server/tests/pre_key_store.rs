@@ -0,0 +1,91 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of verifier pre-key store files
+
+use server::pre_key_store::{PreKeyStore, StoredPreKey, StoredPreKeys};
+
+const PASSWORD: &str = "pre-key-store-test-password";
+
+fn stored_keys() -> StoredPreKeys {
+    StoredPreKeys {
+        identity_secret: [1; 32],
+        pre_keys: vec![
+            StoredPreKey {
+                secret: [2; 32],
+                bundle_id: 1,
+                created_at: 1_000,
+                expires_at: 5_000,
+            },
+            StoredPreKey {
+                secret: [3; 32],
+                bundle_id: 2,
+                created_at: 3_000,
+                expires_at: 7_000,
+            },
+        ],
+    }
+}
+
+#[test]
+fn pre_key_store_round_trip() {
+    let path = std::env::temp_dir().join(format!(
+        "verifier_pre_key_store_round_trip_{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    // a new store holds no keys until it is saved
+    let (store, keys) = PreKeyStore::open(&path, PASSWORD).unwrap();
+    assert!(keys.is_none());
+    store.save(&stored_keys()).unwrap();
+
+    let (store, keys) = PreKeyStore::open(&path, PASSWORD).unwrap();
+    assert_eq!(keys.unwrap(), stored_keys());
+
+    // saving replaces the stored keys
+    let mut new_keys = stored_keys();
+    new_keys.pre_keys.remove(0);
+    store.save(&new_keys).unwrap();
+    let (_, keys) = PreKeyStore::open(&path, PASSWORD).unwrap();
+    assert_eq!(keys.unwrap(), new_keys);
+
+    assert!(PreKeyStore::open(&path, "wrong-password").is_err());
+
+    // secrets are not stored in plain text
+    let content = std::fs::read_to_string(&path).unwrap();
+    assert!(!content.contains(&hex::encode([1u8; 32])));
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn pre_key_store_ciphertext_is_authenticated() {
+    let path = std::env::temp_dir().join(format!(
+        "verifier_pre_key_store_tampered_{}.json",
+        std::process::id()
+    ));
+    let _ = std::fs::remove_file(&path);
+
+    let (store, _) = PreKeyStore::open(&path, PASSWORD).unwrap();
+    store.save(&stored_keys()).unwrap();
+
+    let mut json: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    let mut ciphertext = hex::decode(json["ciphertext"].as_str().unwrap()).unwrap();
+    let last = ciphertext.len() - 1;
+    ciphertext[last] ^= 1;
+    json["ciphertext"] = hex::encode(ciphertext).into();
+    std::fs::write(&path, json.to_string()).unwrap();
+
+    assert!(PreKeyStore::open(&path, PASSWORD).is_err());
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,122 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of pre-key bundle rotation, expiry and persistence
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    PreKeyBundle, VerificationResult, VerifyNumberRequest,
+};
+use chrono::Utc;
+use common::{
+    encrypt_to_bundle, run_test, TestEnv, TestUser, BYPASS_TOKEN, MOBILE_NUMBER, NO_RATE_LIMITS,
+};
+use ed25519_dalek::{PublicKey, SecretKey};
+use server::pre_key_store::PreKeyStore;
+use std::path::PathBuf;
+use std::time::Duration;
+use x25519_dalek::StaticSecret;
+
+const ROTATION_SECS: u64 = 2;
+const GRACE_SECS: u64 = 2;
+const STORE_PASSWORD_ENV: &str = "PRE_KEYS_TEST_STORE_PASSWORD";
+const STORE_PASSWORD: &str = "pre-keys-test-password";
+
+fn store_path() -> PathBuf {
+    std::env::temp_dir().join(format!("verifier_pre_keys_{}.json", std::process::id()))
+}
+
+async fn env() -> &'static TestEnv {
+    std::env::set_var(STORE_PASSWORD_ENV, STORE_PASSWORD);
+    let config = format!(
+        "  pre_keys: {{rotation_secs: {}, grace_secs: {}, store: {}, store_password_env: {}}}\n{}",
+        ROTATION_SECS,
+        GRACE_SECS,
+        store_path().display(),
+        STORE_PASSWORD_ENV,
+        NO_RATE_LIMITS
+    );
+    TestEnv::get(&config).await
+}
+
+/// Returns a bypass token verify number request of a new user with data encrypted to the bundle
+fn encrypted_verify_request(bundle: &PreKeyBundle) -> VerifyNumberRequest {
+    let user = TestUser::generate();
+    let mut data = user.request_data(MOBILE_NUMBER, "", "");
+    data.bypass_token = BYPASS_TOKEN.into();
+    let mut request = user.sign_request(&data);
+    request.encrypted_data = Some(encrypt_to_bundle(bundle, &request.data));
+    request.data = vec![];
+    request
+}
+
+/// Sleeps until the provided time in milliseconds
+async fn sleep_until(time: u64) {
+    let now = Utc::now().timestamp_millis() as u64;
+    if time > now {
+        tokio::time::sleep(Duration::from_millis(time - now)).await;
+    }
+}
+
+#[test]
+fn pre_key_bundle_rotation_and_expiry() {
+    run_test(async {
+        let env = env().await;
+        let bundle = env.get_pre_key_bundle().await;
+        assert_eq!(
+            bundle.expires_at,
+            bundle.created_at + (ROTATION_SECS + GRACE_SECS) * 1000
+        );
+
+        // the bundle is published until it is rotated
+        assert_eq!(env.get_pre_key_bundle().await, bundle);
+
+        // the identity key and the published pre-key are persisted to the store
+        let (_, stored_keys) = PreKeyStore::open(&store_path(), STORE_PASSWORD).unwrap();
+        let stored_keys = stored_keys.unwrap();
+        let identity_secret = SecretKey::from_bytes(&stored_keys.identity_secret).unwrap();
+        assert_eq!(
+            PublicKey::from(&identity_secret).to_bytes().to_vec(),
+            bundle.identity_key
+        );
+        let stored_pre_key = stored_keys
+            .pre_keys
+            .iter()
+            .find(|pre_key| pre_key.bundle_id == bundle.bundle_id)
+            .unwrap();
+        assert_eq!(
+            x25519_dalek::PublicKey::from(&StaticSecret::from(stored_pre_key.secret))
+                .as_bytes()
+                .to_vec(),
+            bundle.pre_key
+        );
+        assert_eq!(stored_pre_key.expires_at, bundle.expires_at);
+
+        sleep_until(bundle.created_at + ROTATION_SECS * 1000 + 100).await;
+        let new_bundle = env.get_pre_key_bundle().await;
+        assert_ne!(new_bundle.bundle_id, bundle.bundle_id);
+        assert_ne!(new_bundle.pre_key, bundle.pre_key);
+        assert_eq!(new_bundle.identity_key, bundle.identity_key);
+        assert!(!new_bundle.signature.is_empty());
+
+        // the replaced bundle decrypts requests during its grace period
+        let resp = env.verify_number(encrypted_verify_request(&bundle)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+
+        let resp = env
+            .verify_number(encrypted_verify_request(&new_bundle))
+            .await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+
+        sleep_until(bundle.expires_at + 100).await;
+        let resp = env.verify_number(encrypted_verify_request(&bundle)).await;
+        assert_eq!(resp.result, VerificationResult::InvalidEncryption as i32);
+
+        let resp = env
+            .verify_number(encrypted_verify_request(&new_bundle))
+            .await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+    })
+}
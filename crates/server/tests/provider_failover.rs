@@ -0,0 +1,160 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of falling back to the configured failover providers when the primary provider fails
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    SendVerificationCodeRequest, SendVerificationCodeResult, VerificationChannel,
+    VerificationResult,
+};
+use common::twilio_stub::{Endpoint, StubResponse};
+use common::{run_test, TestEnv, TestUser, MOBILE_NUMBER, NO_RATE_LIMITS, SID};
+
+const MOCK_CODE: &str = "123456";
+
+async fn env() -> &'static TestEnv {
+    TestEnv::get(&format!(
+        r#"  enabled_channels: [whatsapp, sms]
+  failover:
+    - provider: twilio
+      channel: sms
+    - provider: mock
+{}"#,
+        NO_RATE_LIMITS
+    ))
+    .await
+}
+
+fn send_request() -> SendVerificationCodeRequest {
+    SendVerificationCodeRequest {
+        mobile_number: MOBILE_NUMBER.into(),
+        channel: VerificationChannel::Whatsapp as i32,
+        encrypted_request: None,
+    }
+}
+
+#[test]
+fn primary_provider_sent() {
+    run_test(async {
+        let env = env().await;
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID),
+        );
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+        assert_eq!(resp.session_id, SID);
+        assert_eq!(resp.channel, VerificationChannel::Whatsapp as i32);
+        assert_eq!(env.stub.requests().len(), 1);
+    })
+}
+
+#[test]
+fn failover_to_other_channel() {
+    run_test(async {
+        let env = env().await;
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::error(503));
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID),
+        );
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+        assert_eq!(resp.session_id, SID);
+        assert_eq!(resp.channel, VerificationChannel::Sms as i32);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0].body.contains("Channel=whatsapp"));
+        assert!(requests[1].body.contains("Channel=sms"));
+    })
+}
+
+#[test]
+fn failover_to_other_provider() {
+    run_test(async {
+        let env = env().await;
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::error(500));
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::error(502));
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+        // the session was issued by the mock provider
+        assert!(!resp.session_id.is_empty());
+        assert_ne!(resp.session_id, SID);
+        assert_eq!(resp.channel, VerificationChannel::Whatsapp as i32);
+        assert_eq!(env.stub.requests().len(), 2);
+
+        // the code is checked with the provider which issued the session
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, MOCK_CODE, &resp.session_id);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+        assert_eq!(env.stub.requests().len(), 2);
+    })
+}
+
+#[test]
+fn no_failover_on_rejected_request() {
+    run_test(async {
+        let env = env().await;
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::error(400));
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Failed as i32);
+        assert!(resp.session_id.is_empty());
+        assert_eq!(env.stub.requests().len(), 1);
+    })
+}
+
+#[test]
+fn no_failover_on_invalid_response() {
+    run_test(async {
+        let env = env().await;
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::malformed(201));
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Failed as i32);
+        assert!(resp.session_id.is_empty());
+        assert_eq!(env.stub.requests().len(), 1);
+    })
+}
+
+#[test]
+fn verify_number_failover_channel_session() {
+    run_test(async {
+        let env = env().await;
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::error(503));
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID),
+        );
+        env.stub.push_response(
+            Endpoint::VerificationCheck,
+            StubResponse::verification_check(SID, "approved"),
+        );
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "111111", SID);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[2].endpoint, Endpoint::VerificationCheck);
+    })
+}
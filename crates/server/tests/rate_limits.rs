@@ -0,0 +1,169 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of the verifier's phone number and account rate limits
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    SendVerificationCodeRequest, SendVerificationCodeResult, VerificationChannel,
+    VerificationResult,
+};
+use common::{run_test, TestEnv, TestUser, BYPASS_TOKEN};
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use sp_core::Pair as PairT;
+use tonic::Code;
+
+async fn env() -> &'static TestEnv {
+    TestEnv::get(
+        r#"  rate_limits:
+    phone_number:
+      capacity: 2
+      window_secs: 3600
+    account:
+      capacity: 2
+      window_secs: 3600
+    client_ip:
+      capacity: 0"#,
+    )
+    .await
+}
+
+fn send_request(mobile_number: &str) -> SendVerificationCodeRequest {
+    SendVerificationCodeRequest {
+        mobile_number: mobile_number.into(),
+        channel: VerificationChannel::Unspecified as i32,
+        encrypted_request: None,
+    }
+}
+
+#[test]
+fn phone_number_rate_limit() {
+    run_test(async {
+        let env = env().await;
+        let mobile_number = "+14155552671";
+
+        // requests count towards the limit regardless of the provider result
+        for _ in 0..2 {
+            env.send_verification_code(send_request(mobile_number))
+                .await;
+        }
+
+        let status = env
+            .client()
+            .send_verification_code(send_request(mobile_number))
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+
+        let retry_after: u64 = status
+            .metadata()
+            .get("retry-after")
+            .expect("missing retry-after")
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after > 0 && retry_after <= 1800);
+
+        // other numbers are not limited - formatting doesn't matter
+        env.send_verification_code(send_request("+1 415 555 2672"))
+            .await;
+        let status = env
+            .client()
+            .send_verification_code(send_request("+1 (415) 555-2671"))
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    })
+}
+
+#[test]
+fn phone_number_rate_limit_ignores_rejected_channels() {
+    run_test(async {
+        let env = env().await;
+        let mobile_number = "+14155552675";
+
+        // requests for unknown channels are rejected before they use up the number's quota
+        for _ in 0..3 {
+            let mut request = send_request(mobile_number);
+            request.channel = 99;
+            let resp = env.send_verification_code(request).await;
+            assert_eq!(
+                resp.result,
+                SendVerificationCodeResult::InvalidUserData as i32
+            );
+        }
+
+        for _ in 0..2 {
+            env.send_verification_code(send_request(mobile_number))
+                .await;
+        }
+        let status = env
+            .client()
+            .send_verification_code(send_request(mobile_number))
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+    })
+}
+
+#[test]
+fn account_rate_limit() {
+    run_test(async {
+        let env = env().await;
+        let user = TestUser::generate();
+
+        for _ in 0..2 {
+            let mut data = user.request_data("+14155552673", "", "");
+            data.bypass_token = BYPASS_TOKEN.into();
+            let resp = env.verify_number(user.sign_request(&data)).await;
+            assert_ne!(resp.result, VerificationResult::Unspecified as i32);
+        }
+
+        let mut data = user.request_data("+14155552673", "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let status = env
+            .client()
+            .verify_number(user.sign_request(&data))
+            .await
+            .expect_err("expected rate limit error");
+        assert_eq!(status.code(), Code::ResourceExhausted);
+        assert!(status.metadata().get("retry-after").is_some());
+
+        // other accounts are not limited
+        let other_user = TestUser::generate();
+        let mut data = other_user.request_data("+14155552673", "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let resp = env.verify_number(other_user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+    })
+}
+
+#[test]
+fn account_rate_limit_across_ss58_prefixes() {
+    run_test(async {
+        let env = env().await;
+        let user = TestUser::generate();
+        let account = AccountId32::from(user.pair.public());
+
+        // the same account encoded with different network prefixes shares one bucket
+        for prefix in [0u16, 2, 42] {
+            let mut data = user.request_data("+14155552674", "", "");
+            data.account_id = account.to_ss58check_with_version(Ss58AddressFormat::custom(prefix));
+            data.bypass_token = BYPASS_TOKEN.into();
+            let result = env.client().verify_number(user.sign_request(&data)).await;
+
+            if prefix == 42 {
+                let status = result.expect_err("expected rate limit error");
+                assert_eq!(status.code(), Code::ResourceExhausted);
+            } else {
+                assert_ne!(
+                    result.unwrap().into_inner().result,
+                    VerificationResult::Unspecified as i32
+                );
+            }
+        }
+    })
+}
@@ -0,0 +1,257 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Tests of signing evidence with keys unlocked from keystore files and keys held by a remote signer
+
+mod common;
+
+use base::karma_coin::karma_coin_signer::signer_service_server::{
+    SignerService, SignerServiceServer,
+};
+use base::karma_coin::karma_coin_signer::{
+    GetPublicKeyRequest, GetPublicKeyResponse, SignRequest, SignResponse,
+};
+use base::karma_coin::karma_coin_verifier::{VerificationResult, VerifyNumberResponse};
+use common::{run_test, TestEnv, TestUser, BYPASS_TOKEN, MOBILE_NUMBER, NO_RATE_LIMITS};
+use server::keystore::Keystore;
+use sp_core::ed25519::{Pair as Ed25519Pair, Public, Signature};
+use sp_core::Pair;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::net::UnixListener;
+use tokio_stream::wrappers::UnixListenerStream;
+use tonic::{Request, Response, Status};
+
+const KEYSTORE_PASSWORD_ENV: &str = "SIGNERS_TEST_KEYSTORE_PASSWORD";
+const KEYSTORE_PASSWORD: &str = "signers-test-password";
+/// name of the remote signer's key which signs with another key than its public key
+const ROGUE_KEY_NAME: &str = "rogue";
+
+fn keystore_pair() -> Ed25519Pair {
+    Ed25519Pair::from_string("//KeystoreVerifier", None).unwrap()
+}
+
+fn remote_pair() -> Ed25519Pair {
+    Ed25519Pair::from_string("//RemoteVerifier", None).unwrap()
+}
+
+fn keystore_path() -> PathBuf {
+    std::env::temp_dir().join(format!("verifier_signers_test_{}.json", std::process::id()))
+}
+
+fn socket_path() -> PathBuf {
+    std::env::temp_dir().join(format!("verifier_signers_test_{}.sock", std::process::id()))
+}
+
+/// A remote signer holding the remote key as its default key
+struct TestSigner;
+
+#[tonic::async_trait]
+impl SignerService for TestSigner {
+    async fn get_public_key(
+        &self,
+        request: Request<GetPublicKeyRequest>,
+    ) -> Result<Response<GetPublicKeyResponse>, Status> {
+        match request.into_inner().key_name.as_str() {
+            "" | ROGUE_KEY_NAME => Ok(Response::new(GetPublicKeyResponse {
+                public_key: remote_pair().public().0.to_vec(),
+            })),
+            _ => Err(Status::not_found("unknown key")),
+        }
+    }
+
+    async fn sign(&self, request: Request<SignRequest>) -> Result<Response<SignResponse>, Status> {
+        let request = request.into_inner();
+        let pair = match request.key_name.as_str() {
+            "" => remote_pair(),
+            ROGUE_KEY_NAME => keystore_pair(),
+            _ => return Err(Status::not_found("unknown key")),
+        };
+        Ok(Response::new(SignResponse {
+            signature: pair.sign(&request.data).0.to_vec(),
+        }))
+    }
+}
+
+/// Keys activation times in milliseconds. The most recently activated key signs
+const NOW_ACTIVE: u64 = 2000;
+const NOT_ACTIVE: u64 = 4102444800000;
+
+/// Returns verifier config lines with a keystore key, the remote signer's default key and its rogue key
+fn keys_config(keystore_active_from: u64, rogue_active_from: u64) -> String {
+    format!(
+        r#"  keys:
+    - name: keystore
+      keystore: {}
+      password_env: {}
+      active_from: {}
+    - name: remote
+      remote_signer: {}
+      active_from: 1000
+    - name: rogue
+      remote_signer: {}
+      remote_key_name: {}
+      active_from: {}
+{}"#,
+        keystore_path().display(),
+        KEYSTORE_PASSWORD_ENV,
+        keystore_active_from,
+        socket_path().display(),
+        socket_path().display(),
+        ROGUE_KEY_NAME,
+        rogue_active_from,
+        NO_RATE_LIMITS
+    )
+}
+
+/// The remote key signs with the default config
+fn default_config() -> String {
+    keys_config(0, NOT_ACTIVE)
+}
+
+async fn env() -> &'static TestEnv {
+    static SIGNER: OnceLock<()> = OnceLock::new();
+    SIGNER.get_or_init(|| {
+        Keystore::create(&keystore_pair(), KEYSTORE_PASSWORD)
+            .unwrap()
+            .save(&keystore_path())
+            .unwrap();
+        std::env::set_var(KEYSTORE_PASSWORD_ENV, KEYSTORE_PASSWORD);
+
+        let _ = std::fs::remove_file(socket_path());
+        let listener = UnixListener::bind(socket_path()).unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(SignerServiceServer::new(TestSigner))
+                .serve_with_incoming(UnixListenerStream::new(listener)),
+        );
+    });
+
+    TestEnv::get(&default_config()).await
+}
+
+/// Verifies MOBILE_NUMBER with the bypass token
+async fn verify(env: &TestEnv) -> Result<VerifyNumberResponse, Status> {
+    let user = TestUser::generate();
+    let mut data = user.request_data(MOBILE_NUMBER, "", "");
+    data.bypass_token = BYPASS_TOKEN.into();
+    env.client()
+        .verify_number(user.sign_request(&data))
+        .await
+        .map(|resp| resp.into_inner())
+}
+
+/// Returns the public key the evidence was signed with after checking the signature
+fn evidence_public_key(resp: &VerifyNumberResponse) -> Vec<u8> {
+    assert_eq!(resp.result, VerificationResult::Verified as i32);
+    let data = resp
+        .user_verification_data
+        .as_ref()
+        .expect("missing evidence");
+    let public_key = Public::try_from(data.verifier_public_key.as_slice()).unwrap();
+    let signature = Signature::from_slice(&data.signature).unwrap();
+    assert!(Ed25519Pair::verify(&signature, &data.evidence, &public_key));
+    data.verifier_public_key.clone()
+}
+
+#[test]
+fn remote_signer_signs() {
+    run_test(async {
+        let env = env().await;
+        let resp = verify(env).await.expect("verify number api call failed");
+        assert_eq!(
+            evidence_public_key(&resp),
+            remote_pair().public().0.to_vec()
+        );
+    })
+}
+
+#[test]
+fn keystore_key_signs() {
+    run_test(async {
+        let env = env().await;
+        env.reload(&keys_config(NOW_ACTIVE, NOT_ACTIVE))
+            .await
+            .expect("failed to reload keys");
+        let resp = verify(env).await;
+        env.reload(&default_config())
+            .await
+            .expect("failed to reload keys");
+
+        let resp = resp.expect("verify number api call failed");
+        assert_eq!(
+            evidence_public_key(&resp),
+            keystore_pair().public().0.to_vec()
+        );
+    })
+}
+
+#[test]
+fn single_keystore_key_signs() {
+    run_test(async {
+        let env = env().await;
+        // the keystore takes precedence over the verifier mnemonic when no keys are configured
+        let config = format!(
+            "  keystore: {}\n  keystore_password_env: {}\n{}",
+            keystore_path().display(),
+            KEYSTORE_PASSWORD_ENV,
+            NO_RATE_LIMITS
+        );
+        env.reload(&config).await.expect("failed to reload keys");
+        let resp = verify(env).await;
+        env.reload(&default_config())
+            .await
+            .expect("failed to reload keys");
+
+        let resp = resp.expect("verify number api call failed");
+        assert_eq!(
+            evidence_public_key(&resp),
+            keystore_pair().public().0.to_vec()
+        );
+    })
+}
+
+#[test]
+fn signature_of_another_key_rejected() {
+    run_test(async {
+        let env = env().await;
+        env.reload(&keys_config(0, NOW_ACTIVE))
+            .await
+            .expect("failed to reload keys");
+        let resp = verify(env).await;
+        env.reload(&default_config())
+            .await
+            .expect("failed to reload keys");
+
+        // evidence isn't issued with a signature which doesn't match the advertised key
+        assert_eq!(resp.unwrap_err().code(), tonic::Code::Internal);
+    })
+}
+
+#[test]
+fn unknown_remote_key_rejected() {
+    run_test(async {
+        let env = env().await;
+        let config = default_config().replace(ROGUE_KEY_NAME, "unknown");
+        let reload = env.reload(&config).await;
+        env.reload(&default_config())
+            .await
+            .expect("failed to reload keys");
+        assert!(reload.is_err());
+    })
+}
+
+#[test]
+fn keystore_wrong_password_rejected() {
+    run_test(async {
+        let env = env().await;
+        let config = default_config().replace(KEYSTORE_PASSWORD_ENV, "SIGNERS_TEST_WRONG_PASSWORD");
+        std::env::set_var("SIGNERS_TEST_WRONG_PASSWORD", "wrong-password");
+        let reload = env.reload(&config).await;
+        env.reload(&default_config())
+            .await
+            .expect("failed to reload keys");
+        assert!(reload.is_err());
+    })
+}
@@ -0,0 +1,856 @@
+// Copyright (c) 2022, KarmaCoin Authors. a@karmaco.in.
+// This work is licensed under the KarmaCoin v0.1.0 license published in the LICENSE file of this repo.
+//
+
+//! Contract tests of the verifier api handlers against a Twilio Verify stub
+
+mod common;
+
+use base::karma_coin::karma_coin_verifier::{
+    EvidenceFormat, EvidenceStatus, PhoneNumberHashScheme, SendVerificationCodeRequest,
+    SendVerificationCodeResult, VerificationChannel, VerificationResult,
+};
+use chrono::Utc;
+use common::twilio_stub::{Endpoint, StubResponse};
+use common::{
+    run_test, verifier_public_key, TestEnv, TestUser, BYPASS_TOKEN, MOBILE_NUMBER, NO_RATE_LIMITS,
+    SID,
+};
+use parity_scale_codec::Encode;
+use sp_core::crypto::{AccountId32, Ss58AddressFormat, Ss58Codec};
+use sp_core::ed25519::{Pair as Ed25519Pair, Signature};
+use sp_core::Pair;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// number of a country in the verifier's country blocklist
+const BLOCKED_MOBILE_NUMBER: &str = "+79123456789";
+/// scale encoded evidence tag of the blake2 512 phone number hash scheme
+const BLAKE2_512_SCHEME_TAG: u8 = PhoneNumberHashScheme::Blake2512 as u8;
+
+async fn env() -> &'static TestEnv {
+    TestEnv::get(&format!(
+            "  enabled_channels: [whatsapp, sms]\n  country_blocklist: [7]\n  ss58_prefix: 42\n  past_public_keys: [{}]\n{}",
+            past_verifier_pair().public().to_ss58check(),
+            NO_RATE_LIMITS
+        ),
+    )
+    .await
+}
+
+/// A key the verifier signed evidence with in the past
+fn past_verifier_pair() -> Ed25519Pair {
+    Ed25519Pair::from_string("//PastVerifier", None).expect("invalid past verifier key")
+}
+
+fn send_request() -> SendVerificationCodeRequest {
+    SendVerificationCodeRequest {
+        mobile_number: MOBILE_NUMBER.into(),
+        channel: VerificationChannel::Unspecified as i32,
+        encrypted_request: None,
+    }
+}
+
+/// Creates a verification session with a new session id for MOBILE_NUMBER and clears the stub's requests
+async fn start_session(env: &TestEnv) -> String {
+    static SESSIONS_COUNT: AtomicU32 = AtomicU32::new(100);
+    let sid = format!("VE{:032x}", SESSIONS_COUNT.fetch_add(1, Ordering::Relaxed));
+
+    env.stub.push_response(
+        Endpoint::Verifications,
+        StubResponse::verification_created(&sid),
+    );
+    let resp = env.send_verification_code(send_request()).await;
+    assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+    env.stub.reset();
+    sid
+}
+
+#[test]
+fn send_code_created() {
+    run_test(async {
+        let env = env().await;
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID),
+        );
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+        assert_eq!(resp.session_id, SID);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/v2/Services/VAtest/Verifications");
+        assert!(requests[0].body.contains("To=%2B14155552671"));
+        assert!(requests[0].body.contains("Channel=whatsapp"));
+        assert!(requests[0]
+            .authorization
+            .as_ref()
+            .unwrap()
+            .starts_with("Basic "));
+    })
+}
+
+#[test]
+fn send_code_missing_number() {
+    run_test(async {
+        let env = env().await;
+        let resp = env
+            .send_verification_code(SendVerificationCodeRequest {
+                mobile_number: "".into(),
+                channel: VerificationChannel::Unspecified as i32,
+                encrypted_request: None,
+            })
+            .await;
+        assert_eq!(
+            resp.result,
+            SendVerificationCodeResult::InvalidUserData as i32
+        );
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn send_code_requested_channel() {
+    run_test(async {
+        let env = env().await;
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID),
+        );
+
+        let mut request = send_request();
+        request.channel = VerificationChannel::Sms as i32;
+        let resp = env.send_verification_code(request).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].body.contains("Channel=sms"));
+    })
+}
+
+#[test]
+fn send_code_disabled_channel() {
+    run_test(async {
+        let env = env().await;
+
+        let mut request = send_request();
+        request.channel = VerificationChannel::Voice as i32;
+        let resp = env.send_verification_code(request).await;
+        assert_eq!(
+            resp.result,
+            SendVerificationCodeResult::ChannelNotSupported as i32
+        );
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn send_code_rejected() {
+    run_test(async {
+        let env = env().await;
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::error(400));
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Failed as i32);
+        assert_eq!(resp.error_message, "Code verifier failed to send");
+        assert!(resp.session_id.is_empty());
+    })
+}
+
+#[test]
+fn send_code_malformed_response() {
+    run_test(async {
+        let env = env().await;
+        env.stub
+            .push_response(Endpoint::Verifications, StubResponse::malformed(201));
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Failed as i32);
+        assert_eq!(resp.error_message, "Unexpected code verifier api response");
+    })
+}
+
+#[test]
+fn send_code_timeout() {
+    run_test(async {
+        let env = env().await;
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID).delayed(Duration::from_secs(3)),
+        );
+
+        let resp = env.send_verification_code(send_request()).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Failed as i32);
+        assert_eq!(resp.error_message, "Failed to call code verifier api");
+    })
+}
+
+#[test]
+fn verify_number_approved() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        env.stub.push_response(
+            Endpoint::VerificationCheck,
+            StubResponse::verification_check(&sid, "approved"),
+        );
+
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+        assert_eq!(resp.data.len(), 64);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].path, "/v2/Services/VAtest/VerificationCheck");
+        assert!(requests[0].body.contains("Code=123456"));
+    })
+}
+
+#[test]
+fn verify_number_pending() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        env.stub.push_response(
+            Endpoint::VerificationCheck,
+            StubResponse::verification_check(&sid, "pending"),
+        );
+
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "111111", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Failed as i32);
+        assert!(resp.data.is_empty());
+        assert!(resp.user_verification_data.is_none());
+    })
+}
+
+#[test]
+fn verify_number_sid_mismatch() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        env.stub.push_response(
+            Endpoint::VerificationCheck,
+            StubResponse::verification_check("VE00000000000000000000000000000002", "approved"),
+        );
+
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::MissingData as i32);
+    })
+}
+
+#[test]
+fn verify_number_rejected() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        env.stub
+            .push_response(Endpoint::VerificationCheck, StubResponse::error(404));
+
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Failed as i32);
+    })
+}
+
+#[test]
+fn verify_number_malformed_response() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        env.stub
+            .push_response(Endpoint::VerificationCheck, StubResponse::malformed(200));
+
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Failed as i32);
+    })
+}
+
+#[test]
+fn verify_number_timeout() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        env.stub.push_response(
+            Endpoint::VerificationCheck,
+            StubResponse::verification_check(&sid, "approved").delayed(Duration::from_secs(3)),
+        );
+
+        let user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Failed as i32);
+    })
+}
+
+#[test]
+fn verify_number_invalid_signature() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let other_user = TestUser::generate();
+        let data = user.request_data(MOBILE_NUMBER, "123456", SID);
+        let mut request = user.sign_request(&data);
+        request.signature = other_user.sign_request(&data).signature;
+
+        let resp = env.verify_number(request).await;
+        assert_eq!(resp.result, VerificationResult::InvalidSignature as i32);
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn verify_number_bypass_token() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+        assert!(env.stub.requests().is_empty());
+
+        // the verifier signs the scale encoded evidence and its validity window
+        assert_eq!(resp.expires_at - resp.issued_at, 24 * 60 * 60 * 1000);
+        let evidence = (
+            verifier_public_key(),
+            AccountId32::from(user.pair.public()),
+            "tester".to_string(),
+            sp_core::hashing::blake2_512(MOBILE_NUMBER.as_bytes()),
+            resp.issued_at,
+            resp.expires_at,
+            BLAKE2_512_SCHEME_TAG,
+        )
+            .encode();
+        let signature = Signature::from_slice(&resp.data).expect("invalid signature");
+        assert!(Ed25519Pair::verify(
+            &signature,
+            &evidence,
+            &verifier_public_key()
+        ));
+
+        // the evidence is returned as signed so clients can forward it verbatim
+        let user_verification_data = resp.user_verification_data.expect("missing evidence");
+        assert_eq!(user_verification_data.evidence, evidence);
+        assert_eq!(user_verification_data.signature, resp.data);
+        assert_eq!(
+            user_verification_data.verifier_public_key,
+            verifier_public_key().0.to_vec()
+        );
+    })
+}
+
+#[test]
+fn verify_number_unknown_session() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let data = user.request_data(
+            MOBILE_NUMBER,
+            "123456",
+            "VE00000000000000000000000000000003",
+        );
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::InvalidSession as i32);
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn verify_number_session_of_other_number() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+
+        let user = TestUser::generate();
+        let data = user.request_data("+14155552672", "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::InvalidSession as i32);
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn verify_number_stale_request() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        data.timestamp -= 60 * 60 * 1000;
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::StaleRequest as i32);
+    })
+}
+
+#[test]
+fn verify_number_duplicate_request() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let request = user.sign_request(&data);
+
+        let resp = env.verify_number(request.clone()).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+
+        let resp = env.verify_number(request).await;
+        assert_eq!(resp.result, VerificationResult::DuplicateRequest as i32);
+    })
+}
+
+#[test]
+fn send_code_formatted_number() {
+    run_test(async {
+        let env = env().await;
+        env.stub.push_response(
+            Endpoint::Verifications,
+            StubResponse::verification_created(SID),
+        );
+
+        let mut request = send_request();
+        request.mobile_number = "+1 (415) 555-2671".into();
+        let resp = env.send_verification_code(request).await;
+        assert_eq!(resp.result, SendVerificationCodeResult::Sent as i32);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].body.contains("To=%2B14155552671"));
+    })
+}
+
+#[test]
+fn send_code_invalid_number() {
+    run_test(async {
+        let env = env().await;
+
+        let mut request = send_request();
+        request.mobile_number = "not a number".into();
+        let resp = env.send_verification_code(request).await;
+        assert_eq!(
+            resp.result,
+            SendVerificationCodeResult::InvalidUserData as i32
+        );
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn verify_number_formatted_number() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        env.stub.push_response(
+            Endpoint::VerificationCheck,
+            StubResponse::verification_check(&sid, "approved"),
+        );
+
+        let user = TestUser::generate();
+        let data = user.request_data("+1 415-555-2671", "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].body.contains("To=%2B14155552671"));
+    })
+}
+
+#[test]
+fn verify_number_invalid_number() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data("12345", "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::MissingData as i32);
+    })
+}
+
+#[test]
+fn send_code_blocked_region() {
+    run_test(async {
+        let env = env().await;
+
+        let mut request = send_request();
+        request.mobile_number = BLOCKED_MOBILE_NUMBER.into();
+        let resp = env.send_verification_code(request).await;
+        assert_eq!(
+            resp.result,
+            SendVerificationCodeResult::RegionNotSupported as i32
+        );
+        assert!(env.stub.requests().is_empty());
+    })
+}
+
+#[test]
+fn verify_number_blocked_region() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(BLOCKED_MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::RegionNotSupported as i32);
+    })
+}
+
+#[test]
+fn verify_number_too_many_attempts() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        for _ in 0..5 {
+            env.stub.push_response(
+                Endpoint::VerificationCheck,
+                StubResponse::verification_check(&sid, "pending"),
+            );
+        }
+        env.stub.push_response(
+            Endpoint::UpdateVerification,
+            StubResponse::verification_check(&sid, "canceled"),
+        );
+
+        let user = TestUser::generate();
+        for _ in 0..5 {
+            let data = user.request_data(MOBILE_NUMBER, "111111", &sid);
+            let resp = env.verify_number(user.sign_request(&data)).await;
+            assert_eq!(resp.result, VerificationResult::Failed as i32);
+            // requests of the same millisecond would be rejected as duplicates
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+
+        // the session is canceled with the provider once it's locked
+        let requests = env.stub.requests();
+        assert_eq!(requests.len(), 6);
+        assert_eq!(requests[5].endpoint, Endpoint::UpdateVerification);
+        assert_eq!(
+            requests[5].path,
+            format!("/v2/Services/VAtest/Verifications/{}", sid)
+        );
+        assert!(requests[5].body.contains("Status=canceled"));
+
+        let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::TooManyAttempts as i32);
+        assert_eq!(env.stub.requests().len(), 6);
+    })
+}
+
+#[test]
+fn verify_number_provider_errors_not_counted() {
+    run_test(async {
+        let env = env().await;
+        let sid = start_session(env).await;
+        for _ in 0..5 {
+            env.stub
+                .push_response(Endpoint::VerificationCheck, StubResponse::malformed(200));
+        }
+        env.stub.push_response(
+            Endpoint::VerificationCheck,
+            StubResponse::verification_check(&sid, "approved"),
+        );
+
+        let user = TestUser::generate();
+        for _ in 0..5 {
+            let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+            let resp = env.verify_number(user.sign_request(&data)).await;
+            assert_eq!(resp.result, VerificationResult::Failed as i32);
+            tokio::time::sleep(Duration::from_millis(2)).await;
+        }
+
+        let data = user.request_data(MOBILE_NUMBER, "123456", &sid);
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::Verified as i32);
+    })
+}
+
+#[test]
+fn verify_number_malformed_signature() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let mut request = user.sign_request(&data);
+        request.signature.truncate(10);
+
+        let resp = env.verify_number(request).await;
+        assert_eq!(resp.result, VerificationResult::InvalidSignature as i32);
+    })
+}
+
+#[test]
+fn verify_number_invalid_account_id() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        data.account_id = "not an account id".into();
+
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::MissingData as i32);
+    })
+}
+
+#[test]
+fn verify_number_unexpected_network_prefix() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        data.account_id = user
+            .pair
+            .public()
+            .to_ss58check_with_version(Ss58AddressFormat::custom(0));
+
+        let resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(resp.result, VerificationResult::MissingData as i32);
+    })
+}
+
+#[test]
+fn verify_number_malformed_data() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut request = user.sign_request(&user.request_data(MOBILE_NUMBER, "", ""));
+        request.data = vec![0xff; 16];
+
+        let resp = env.verify_number(request).await;
+        assert_eq!(resp.result, VerificationResult::MissingData as i32);
+    })
+}
+
+/// Verifies MOBILE_NUMBER with the bypass token and the provided user name
+async fn verify_user_name(env: &TestEnv, user_name: &str) -> i32 {
+    let user = TestUser::generate();
+    let mut data = user.request_data(MOBILE_NUMBER, "", "");
+    data.bypass_token = BYPASS_TOKEN.into();
+    data.user_name = user_name.into();
+    env.verify_number(user.sign_request(&data)).await.result
+}
+
+#[test]
+fn verify_number_user_name_policy() {
+    run_test(async {
+        let env = env().await;
+
+        for user_name in ["ab", "tester!", "test er", "Adm1n", "аdmin", "karma_coin"] {
+            assert_eq!(
+                verify_user_name(env, user_name).await,
+                VerificationResult::InvalidUserName as i32,
+                "user name: {}",
+                user_name
+            );
+        }
+
+        // names are normalized before they are validated
+        for user_name in ["ｔｅｓｔｅｒ", "tester_1", "Élodie"] {
+            assert_eq!(
+                verify_user_name(env, user_name).await,
+                VerificationResult::Verified as i32,
+                "user name: {}",
+                user_name
+            );
+        }
+    })
+}
+
+/// Returns scale encoded evidence signed by the provided verifier key for a new user
+fn signed_evidence(verifier: &Ed25519Pair, expires_at: u64) -> (Vec<u8>, Vec<u8>) {
+    let user = TestUser::generate();
+    let evidence = (
+        verifier.public(),
+        AccountId32::from(user.pair.public()),
+        "tester".to_string(),
+        sp_core::hashing::blake2_512(MOBILE_NUMBER.as_bytes()),
+        expires_at - 1000,
+        expires_at,
+        BLAKE2_512_SCHEME_TAG,
+    )
+        .encode();
+    let signature = verifier.sign(&evidence).0.to_vec();
+    (evidence, signature)
+}
+
+#[test]
+fn verify_evidence_valid() {
+    run_test(async {
+        let env = env().await;
+
+        let user = TestUser::generate();
+        let mut data = user.request_data(MOBILE_NUMBER, "", "");
+        data.bypass_token = BYPASS_TOKEN.into();
+        let verify_resp = env.verify_number(user.sign_request(&data)).await;
+        assert_eq!(verify_resp.result, VerificationResult::Verified as i32);
+        let user_verification_data = verify_resp
+            .user_verification_data
+            .expect("missing evidence");
+
+        let resp = env
+            .verify_evidence(
+                user_verification_data.evidence,
+                user_verification_data.signature,
+            )
+            .await;
+        assert_eq!(resp.status, EvidenceStatus::Valid as i32);
+        assert!(resp.signed_by_current_key);
+        assert_eq!(resp.verifier_public_key, verifier_public_key().0.to_vec());
+        assert_eq!(
+            resp.account_id,
+            AccountId32::from(user.pair.public())
+                .to_ss58check_with_version(Ss58AddressFormat::custom(42))
+        );
+        assert_eq!(resp.user_name, "tester");
+        assert_eq!(
+            resp.phone_number_hash,
+            sp_core::hashing::blake2_512(MOBILE_NUMBER.as_bytes()).to_vec()
+        );
+        assert_eq!(
+            resp.phone_number_hash_scheme,
+            PhoneNumberHashScheme::Blake2512 as i32
+        );
+        assert_eq!(resp.issued_at, verify_resp.issued_at);
+        assert_eq!(resp.expires_at, verify_resp.expires_at);
+    })
+}
+
+#[test]
+fn verify_evidence_past_key() {
+    run_test(async {
+        let env = env().await;
+
+        let expires_at = Utc::now().timestamp_millis() as u64 + 60 * 1000;
+        let (evidence, signature) = signed_evidence(&past_verifier_pair(), expires_at);
+        let resp = env.verify_evidence(evidence, signature).await;
+        assert_eq!(resp.status, EvidenceStatus::Valid as i32);
+        assert!(!resp.signed_by_current_key);
+        assert_eq!(
+            resp.verifier_public_key,
+            past_verifier_pair().public().0.to_vec()
+        );
+    })
+}
+
+#[test]
+fn verify_evidence_expired() {
+    run_test(async {
+        let env = env().await;
+
+        let expires_at = Utc::now().timestamp_millis() as u64 - 1000;
+        let (evidence, signature) = signed_evidence(&past_verifier_pair(), expires_at);
+        let resp = env.verify_evidence(evidence, signature).await;
+        assert_eq!(resp.status, EvidenceStatus::Expired as i32);
+        assert_eq!(resp.expires_at, expires_at);
+    })
+}
+
+#[test]
+fn verify_evidence_unknown_key() {
+    run_test(async {
+        let env = env().await;
+
+        let (other_verifier, _) = Ed25519Pair::generate();
+        let expires_at = Utc::now().timestamp_millis() as u64 + 60 * 1000;
+        let (evidence, signature) = signed_evidence(&other_verifier, expires_at);
+        let resp = env.verify_evidence(evidence, signature).await;
+        assert_eq!(resp.status, EvidenceStatus::InvalidSignature as i32);
+        assert!(resp.account_id.is_empty());
+    })
+}
+
+#[test]
+fn verify_evidence_tampered() {
+    run_test(async {
+        let env = env().await;
+
+        let expires_at = Utc::now().timestamp_millis() as u64 + 60 * 1000;
+        let (mut evidence, signature) = signed_evidence(&past_verifier_pair(), expires_at);
+        // extend the evidence's validity window - expires_at is followed by the hash scheme tag
+        let len = evidence.len();
+        evidence[len - 2] ^= 0x01;
+        let resp = env.verify_evidence(evidence, signature).await;
+        assert_eq!(resp.status, EvidenceStatus::InvalidSignature as i32);
+    })
+}
+
+#[test]
+fn verify_evidence_malformed() {
+    run_test(async {
+        let env = env().await;
+
+        let resp = env.verify_evidence(vec![1, 2, 3], vec![0; 64]).await;
+        assert_eq!(resp.status, EvidenceStatus::Malformed as i32);
+
+        // trailing bytes aren't part of the signed evidence
+        let expires_at = Utc::now().timestamp_millis() as u64 + 60 * 1000;
+        let (mut evidence, signature) = signed_evidence(&past_verifier_pair(), expires_at);
+        evidence.push(0);
+        let resp = env.verify_evidence(evidence, signature).await;
+        assert_eq!(resp.status, EvidenceStatus::Malformed as i32);
+    })
+}
+
+#[test]
+fn get_verifier_info() {
+    run_test(async {
+        let env = env().await;
+
+        let info = env.get_verifier_info().await;
+        assert_eq!(info.name, "Test Verifier");
+        assert_eq!(info.public_key, verifier_public_key().0.to_vec());
+        assert_eq!(
+            info.public_key_ss58,
+            verifier_public_key().to_ss58check_with_version(Ss58AddressFormat::custom(42))
+        );
+        assert_eq!(
+            info.past_public_keys,
+            vec![past_verifier_pair().public().0.to_vec()]
+        );
+        assert_eq!(
+            info.channels,
+            vec![
+                VerificationChannel::Whatsapp as i32,
+                VerificationChannel::Sms as i32
+            ]
+        );
+        assert_eq!(
+            info.evidence_formats,
+            vec![EvidenceFormat::ScaleEd25519V2 as i32]
+        );
+        assert_eq!(
+            info.phone_number_hash_scheme,
+            PhoneNumberHashScheme::Blake2512 as i32
+        );
+        assert!(!info.api_version.is_empty());
+        assert!(env.stub.requests().is_empty());
+    })
+}